@@ -0,0 +1,416 @@
+use std::ops::RangeInclusive;
+
+use egui::remap;
+
+use crate::TimeCursor;
+
+/// Shape of the segment from a point to the next one
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AutomationSegment {
+    /// Holds the value until the next point
+    Step,
+    #[default]
+    Linear,
+    /// Bends the segment, positive tension starts slow and ends fast. Ranges from -1 to 1.
+    Curve { tension: f32 },
+}
+impl AutomationSegment {
+    /// Maps the position `t` in `0..=1` along the segment to the interpolation factor
+    pub fn shape(&self, t: f32) -> f32 {
+        match *self {
+            AutomationSegment::Step => 0.0,
+            AutomationSegment::Linear => t,
+            AutomationSegment::Curve { tension } => t.powf(8.0f32.powf(tension.clamp(-1.0, 1.0))),
+        }
+    }
+
+    /// Step -> Linear -> Curve -> Step
+    fn next(&self) -> Self {
+        match self {
+            AutomationSegment::Step => AutomationSegment::Linear,
+            AutomationSegment::Linear => AutomationSegment::Curve { tension: 0.5 },
+            AutomationSegment::Curve { .. } => AutomationSegment::Step,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutomationPoint {
+    pub time:    f32,
+    pub value:   f32,
+    pub segment: AutomationSegment,
+}
+impl AutomationPoint {
+    pub fn new(time: f32, value: f32) -> Self {
+        Self {
+            time,
+            value,
+            segment: AutomationSegment::default(),
+        }
+    }
+
+    pub fn with_segment(self, segment: AutomationSegment) -> Self { Self { segment, ..self } }
+}
+
+/// Value of the automation at `time`. `points` have to be sorted by time, the first and last value
+/// are held outside of them.
+pub fn automation_value(points: &[AutomationPoint], time: f32) -> Option<f32> {
+    let next = points.partition_point(|p| p.time <= time);
+    match (next.checked_sub(1).map(|i| points[i]), points.get(next)) {
+        (None, None) => None,
+        (Some(p), None) => Some(p.value),
+        (None, Some(p)) => Some(p.value),
+        // Points dragged onto each other jump straight to the later value
+        (Some(a), Some(b)) if b.time <= a.time => Some(b.value),
+        (Some(a), Some(b)) => {
+            let t = a.segment.shape((time - a.time) / (b.time - a.time));
+            Some(egui::lerp(a.value..=b.value, t))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AutomationUnit {
+    #[default]
+    None,
+    Decibels,
+    /// Shown on a logarithmic scale
+    Hertz,
+    Percent,
+}
+impl AutomationUnit {
+    pub fn format(&self, value: f32) -> String {
+        match self {
+            AutomationUnit::None => format!("{value:.2}"),
+            AutomationUnit::Decibels => format!("{value:.1} dB"),
+            AutomationUnit::Hertz if value >= 1000.0 => format!("{:.2} kHz", value / 1000.0),
+            AutomationUnit::Hertz => format!("{value:.0} Hz"),
+            AutomationUnit::Percent => format!("{value:.0}%"),
+        }
+    }
+}
+
+/// What a primary button drag started on. Kept in egui's temporary memory while the drag lasts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AutomationDrag {
+    Point(usize),
+    /// Bends the curved segment starting at the point
+    Tension(usize),
+}
+
+/// Breakpoint automation drawn against a [`TimeCursor`], so it scrolls and zooms together with a
+/// [`crate::Waveform`] bound to the same cursor.
+///
+/// Drag points to move them, double click to add or remove points, right click a point to cycle
+/// the shape of its segment and drag a curved segment vertically to bend it.
+pub struct AutomationLane<'a> {
+    points: &'a mut Vec<AutomationPoint>,
+    cursor: Option<&'a mut TimeCursor>,
+    range: RangeInclusive<f32>,
+    unit: AutomationUnit,
+    default_segment: AutomationSegment,
+    height: f32,
+    stroke: Option<egui::Stroke>,
+}
+impl<'a> AutomationLane<'a> {
+    /// `points` are kept sorted by time
+    pub fn new(points: &'a mut Vec<AutomationPoint>) -> Self {
+        Self {
+            points,
+            cursor: None,
+            range: 0.0..=1.0,
+            unit: AutomationUnit::None,
+            default_segment: AutomationSegment::default(),
+            height: 80.0,
+            stroke: None,
+        }
+    }
+
+    pub fn cursor(self, cursor: &'a mut TimeCursor) -> Self {
+        Self {
+            cursor: Some(cursor),
+            ..self
+        }
+    }
+
+    pub fn range(self, range: RangeInclusive<f32>) -> Self { Self { range, ..self } }
+
+    pub fn unit(self, unit: AutomationUnit) -> Self { Self { unit, ..self } }
+
+    /// Segment shape of points added by double clicking
+    pub fn default_segment(self, default_segment: AutomationSegment) -> Self {
+        Self {
+            default_segment,
+            ..self
+        }
+    }
+
+    pub fn height(self, height: f32) -> Self { Self { height, ..self } }
+
+    pub fn stroke(self, stroke: impl Into<egui::Stroke>) -> Self {
+        Self {
+            stroke: Some(stroke.into()),
+            ..self
+        }
+    }
+}
+
+/// Maps values to `0..=1`, logarithmically for frequencies
+#[derive(Debug, Clone)]
+struct ValueScale {
+    range: RangeInclusive<f32>,
+    logarithmic: bool,
+}
+impl ValueScale {
+    fn new(range: RangeInclusive<f32>, unit: AutomationUnit) -> Self {
+        Self {
+            logarithmic: unit == AutomationUnit::Hertz && *range.start() > 0.0,
+            range,
+        }
+    }
+
+    fn normalize(&self, value: f32) -> f32 {
+        if self.logarithmic {
+            remap(
+                value.max(*self.range.start()).ln(),
+                self.range.start().ln()..=self.range.end().ln(),
+                0.0..=1.0,
+            )
+        }
+        else {
+            remap(value, self.range.clone(), 0.0..=1.0)
+        }
+    }
+
+    fn denormalize(&self, normalized: f32) -> f32 {
+        if self.logarithmic {
+            remap(
+                normalized,
+                0.0..=1.0,
+                self.range.start().ln()..=self.range.end().ln(),
+            )
+            .exp()
+        }
+        else {
+            remap(normalized, 0.0..=1.0, self.range.clone())
+        }
+    }
+}
+impl<'a> egui::Widget for AutomationLane<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        const GRAB_RADIUS: f32 = 6.0;
+
+        let points_range = match (self.points.first(), self.points.last()) {
+            (Some(first), Some(last)) if first.time < last.time => first.time..last.time,
+            _ => 0.0..1.0,
+        };
+        let mut fallback_cursor = TimeCursor::from(points_range.clone());
+        let (rect, mut response) = ui.allocate_at_least(
+            egui::vec2(ui.available_width(), self.height),
+            egui::Sense::click_and_drag(),
+        );
+
+        let scale = ValueScale::new(self.range, self.unit);
+        let points = self.points;
+        let cursor: &mut TimeCursor = self.cursor.unwrap_or(&mut fallback_cursor);
+        cursor.initialize_if_empty(points_range);
+        cursor.handle_input(ui, &response, rect);
+
+        let value_rect = rect.shrink2(egui::vec2(0.0, GRAB_RADIUS));
+        let to_pos = |time: f32, value: f32| {
+            egui::pos2(
+                remap(time, cursor.time_range_inclusive(), rect.x_range()),
+                remap(
+                    scale.normalize(value),
+                    0.0..=1.0,
+                    value_rect.bottom()..=value_rect.top(),
+                ),
+            )
+        };
+        let from_pos = |pos: egui::Pos2| {
+            let time = remap(pos.x, rect.x_range(), cursor.time_range_inclusive());
+            let normalized =
+                egui::remap_clamp(pos.y, value_rect.bottom()..=value_rect.top(), 0.0..=1.0);
+            (time, scale.denormalize(normalized))
+        };
+        let point_at = |points: &[AutomationPoint], pos: egui::Pos2| {
+            points
+                .iter()
+                .position(|p| to_pos(p.time, p.value).distance(pos) <= GRAB_RADIUS)
+        };
+
+        // Editing
+        let drag_id = response.id.with("automation_drag");
+        if response.drag_started_by(egui::PointerButton::Primary) {
+            let drag = ui.input(|i| i.pointer.press_origin()).and_then(|origin| {
+                point_at(points, origin)
+                    .map(AutomationDrag::Point)
+                    .or_else(|| {
+                        let (time, _) = from_pos(origin);
+                        let index = points.partition_point(|p| p.time <= time);
+                        let segment = index.checked_sub(1)?;
+                        (index < points.len()
+                            && matches!(points[segment].segment, AutomationSegment::Curve { .. }))
+                        .then_some(AutomationDrag::Tension(segment))
+                    })
+            });
+            ui.data_mut(|d| d.insert_temp(drag_id, drag));
+        }
+
+        let drag = if response.dragged_by(egui::PointerButton::Primary) {
+            ui.data(|d| d.get_temp::<Option<AutomationDrag>>(drag_id))
+                .flatten()
+        }
+        else {
+            None
+        };
+        if response.drag_stopped() {
+            ui.data_mut(|d| d.remove::<Option<AutomationDrag>>(drag_id));
+        }
+
+        match drag {
+            // The point was removed while dragging it
+            Some(AutomationDrag::Point(index) | AutomationDrag::Tension(index))
+                if index >= points.len() =>
+            {
+                ui.data_mut(|d| d.remove::<Option<AutomationDrag>>(drag_id));
+            }
+            Some(AutomationDrag::Point(index)) => {
+                if let Some(pointer) = response.interact_pointer_pos() {
+                    let (time, value) = from_pos(pointer);
+                    let min = index
+                        .checked_sub(1)
+                        .map_or(f32::NEG_INFINITY, |i| points[i].time);
+                    let max = points.get(index + 1).map_or(f32::INFINITY, |p| p.time);
+                    let point = &mut points[index];
+                    point.time = time.min(max).max(min);
+                    point.value = value;
+                    response.mark_changed();
+                }
+            }
+            Some(AutomationDrag::Tension(index)) => {
+                // Dragging up bends the curve upwards
+                let rising = points
+                    .get(index + 1)
+                    .is_some_and(|next| next.value >= points[index].value);
+                let delta = response.drag_delta().y / value_rect.height() * 2.0;
+                if let AutomationSegment::Curve { tension } = &mut points[index].segment {
+                    *tension = (*tension + if rising { delta } else { -delta }).clamp(-1.0, 1.0);
+                    response.mark_changed();
+                }
+            }
+            None => {}
+        }
+
+        if response.double_clicked()
+            && let Some(pos) = response.interact_pointer_pos()
+        {
+            if let Some(index) = point_at(points, pos) {
+                points.remove(index);
+            }
+            else {
+                let (time, value) = from_pos(pos);
+                let index = points.partition_point(|p| p.time <= time);
+                points.insert(
+                    index,
+                    AutomationPoint::new(time, value).with_segment(self.default_segment),
+                );
+            }
+            response.mark_changed();
+        }
+
+        if response.secondary_clicked()
+            && let Some(pos) = response.interact_pointer_pos()
+            && let Some(index) = point_at(points, pos)
+            && let Some(point) = points.get_mut(index)
+        {
+            point.segment = point.segment.next();
+            response.mark_changed();
+        }
+
+        // Drawing
+        let visuals = ui.style().visuals.clone();
+        let painter = ui.painter_at(rect);
+        painter.rect(
+            rect,
+            visuals.widgets.noninteractive.corner_radius,
+            visuals.extreme_bg_color,
+            visuals.widgets.noninteractive.bg_stroke,
+            egui::StrokeKind::Inside,
+        );
+
+        for normalized in [0.0, 0.5, 1.0] {
+            let value = scale.denormalize(normalized);
+            let y = to_pos(0.0, value).y;
+            painter.hline(
+                rect.x_range(),
+                y,
+                (1.0, visuals.widgets.noninteractive.bg_stroke.color),
+            );
+            painter.text(
+                egui::pos2(rect.left() + 2.0, y),
+                if normalized == 1.0 {
+                    egui::Align2::LEFT_TOP
+                }
+                else {
+                    egui::Align2::LEFT_BOTTOM
+                },
+                self.unit.format(value),
+                egui::FontId::proportional(9.0),
+                visuals.weak_text_color(),
+            );
+        }
+
+        let stroke = self
+            .stroke
+            .unwrap_or(visuals.widgets.noninteractive.fg_stroke);
+        if let (Some(first), Some(last)) = (points.first(), points.last()) {
+            let mut line = vec![egui::pos2(rect.left(), to_pos(first.time, first.value).y)];
+            for segment in points.windows(2) {
+                let [a, b] = [segment[0], segment[1]];
+                match a.segment {
+                    AutomationSegment::Step => {
+                        line.push(to_pos(a.time, a.value));
+                        line.push(to_pos(b.time, a.value));
+                    }
+                    AutomationSegment::Linear => line.push(to_pos(a.time, a.value)),
+                    AutomationSegment::Curve { .. } => {
+                        const NUM_POINTS: usize = 24;
+                        line.extend((0..NUM_POINTS).map(|i| {
+                            let t = i as f32 / NUM_POINTS as f32;
+                            let value = egui::lerp(a.value..=b.value, a.segment.shape(t));
+                            to_pos(egui::lerp(a.time..=b.time, t), value)
+                        }));
+                    }
+                }
+            }
+            line.push(to_pos(last.time, last.value));
+            line.push(egui::pos2(rect.right(), to_pos(last.time, last.value).y));
+            painter.add(egui::Shape::line(line, stroke));
+        }
+
+        let hovered = response.hover_pos().and_then(|pos| point_at(points, pos));
+        let active = match drag {
+            Some(AutomationDrag::Point(index)) => Some(index),
+            _ => hovered,
+        };
+        for (i, point) in points.iter().enumerate() {
+            let pos = to_pos(point.time, point.value);
+            if active == Some(i) {
+                painter.circle(pos, 5.0, visuals.extreme_bg_color, (1.5, stroke.color));
+                painter.text(
+                    pos + egui::vec2(6.0, -6.0),
+                    egui::Align2::LEFT_BOTTOM,
+                    self.unit.format(point.value),
+                    egui::FontId::proportional(10.0),
+                    visuals.strong_text_color(),
+                );
+            }
+            else {
+                painter.circle(pos, 3.0, visuals.extreme_bg_color, stroke);
+            }
+        }
+
+        response
+    }
+}
@@ -2,6 +2,7 @@ use std::ops::RangeInclusive;
 
 use egui::Vec2;
 
+use crate::LevelMeter;
 use crate::util::{from_db_deadzone, to_db_deadzone};
 
 #[derive(Debug)]
@@ -13,6 +14,7 @@ pub struct Fader<'a> {
     convert_to_db: bool,
     show_value: bool,
     label: Option<String>,
+    meter: Option<LevelMeter<'a>>,
 }
 impl<'a> Fader<'a> {
     pub fn volume(value: &'a mut f32) -> Self {
@@ -24,6 +26,7 @@ impl<'a> Fader<'a> {
             convert_to_db: true,
             show_value: true,
             label: None,
+            meter: None,
         }
     }
 
@@ -48,6 +51,15 @@ impl<'a> Fader<'a> {
             ..self
         }
     }
+
+    /// Draws a level meter inside the fader track. If the fader converts to dB, the meter uses
+    /// the fader's range so gain and level share the same scale.
+    pub fn meter(self, meter: LevelMeter<'a>) -> Self {
+        Self {
+            meter: Some(meter),
+            ..self
+        }
+    }
 }
 impl<'a> egui::Widget for Fader<'a> {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
@@ -80,11 +92,32 @@ impl<'a> egui::Widget for Fader<'a> {
 
             let visuals = ui.style().interact(&res);
 
-            ui.painter().rect_filled(
-                egui::Rect::from_center_size(rect.center(), egui::vec2(5.0, rect.height())),
-                visuals.corner_radius,
-                ui.style().visuals.extreme_bg_color,
-            );
+            if let Some(mut meter) = self.meter {
+                if self.convert_to_db {
+                    meter = meter.range(self.range.clone());
+                }
+                let meter_width = (rect.width() * 0.3).max(5.0);
+                let meter_rect = egui::Rect::from_center_size(
+                    rect.center(),
+                    egui::vec2(meter_width, rect.height() - handle_height),
+                );
+                let led_rect = egui::Rect::from_min_max(
+                    egui::pos2(meter_rect.left(), rect.top()),
+                    egui::pos2(meter_rect.right(), meter_rect.top() - 2.0),
+                );
+                let led_clicked = res.clicked()
+                    && res
+                        .interact_pointer_pos()
+                        .is_some_and(|pos| led_rect.contains(pos));
+                meter.paint(ui, meter_rect, led_rect, led_clicked);
+            }
+            else {
+                ui.painter().rect_filled(
+                    egui::Rect::from_center_size(rect.center(), egui::vec2(5.0, rect.height())),
+                    visuals.corner_radius,
+                    ui.style().visuals.extreme_bg_color,
+                );
+            }
 
             let handle_rect = egui::Rect::from_center_size(
                 rect.center_top()
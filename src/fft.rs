@@ -0,0 +1,75 @@
+use std::f32::consts::TAU;
+
+/// In-place iterative radix-2 FFT. `re.len()` must be a power of two.
+pub(crate) fn fft_in_place(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    assert_eq!(n, im.len());
+    assert!(n.is_power_of_two(), "FFT size must be a power of two");
+
+    // Bit reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -TAU / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let a = start + k;
+                let b = a + len / 2;
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+pub(crate) fn hann_window(len: usize) -> impl Iterator<Item = f32> {
+    (0..len).map(move |i| 0.5 - 0.5 * (TAU * i as f32 / len as f32).cos())
+}
+
+/// Windowed magnitude spectrum of the last `fft_size` samples, `fft_size / 2 + 1` bins.
+/// Normalized so that a full scale sine wave peaks at 1.0.
+pub(crate) fn magnitude_spectrum(samples: &[f32], fft_size: usize) -> Vec<f32> {
+    let samples = &samples[samples.len().saturating_sub(fft_size)..];
+
+    let mut re = vec![0.0; fft_size];
+    let mut im = vec![0.0; fft_size];
+
+    let mut window_sum = 0.0;
+    for ((out, &sample), w) in re.iter_mut().zip(samples).zip(hann_window(fft_size)) {
+        *out = sample * w;
+        window_sum += w;
+    }
+
+    fft_in_place(&mut re, &mut im);
+
+    let scale = if window_sum > 0.0 {
+        2.0 / window_sum
+    }
+    else {
+        0.0
+    };
+    re.iter()
+        .zip(im.iter())
+        .take(fft_size / 2 + 1)
+        .map(|(re, im)| (re * re + im * im).sqrt() * scale)
+        .collect()
+}
@@ -0,0 +1,163 @@
+use std::ops::RangeInclusive;
+
+/// Maps between frequency/dB values and screen space on a log-frequency / dB plot.
+#[derive(Debug, Clone)]
+pub(crate) struct FrequencyGrid {
+    pub rect: egui::Rect,
+    pub frequency_range: RangeInclusive<f32>,
+    pub db_range: RangeInclusive<f32>,
+}
+impl FrequencyGrid {
+    pub fn new(
+        rect: egui::Rect,
+        frequency_range: RangeInclusive<f32>,
+        db_range: RangeInclusive<f32>,
+    ) -> Self {
+        Self {
+            rect,
+            frequency_range,
+            db_range,
+        }
+    }
+
+    pub fn x_for_frequency(&self, frequency: f32) -> f32 {
+        egui::remap(
+            frequency.max(f32::MIN_POSITIVE).log10(),
+            self.frequency_range.start().log10()..=self.frequency_range.end().log10(),
+            self.rect.x_range(),
+        )
+    }
+
+    pub fn frequency_for_x(&self, x: f32) -> f32 {
+        10.0f32.powf(egui::remap(
+            x,
+            self.rect.x_range(),
+            self.frequency_range.start().log10()..=self.frequency_range.end().log10(),
+        ))
+    }
+
+    pub fn y_for_db(&self, db: f32) -> f32 {
+        egui::remap(
+            db,
+            *self.db_range.end()..=*self.db_range.start(),
+            self.rect.y_range(),
+        )
+    }
+
+    pub fn db_for_y(&self, y: f32) -> f32 {
+        egui::remap(
+            y,
+            self.rect.y_range(),
+            *self.db_range.end()..=*self.db_range.start(),
+        )
+    }
+
+    pub fn pos(&self, frequency: f32, db: f32) -> egui::Pos2 {
+        egui::pos2(self.x_for_frequency(frequency), self.y_for_db(db))
+    }
+
+    pub fn paint(&self, painter: &egui::Painter, style: &egui::Style) {
+        let visuals = &style.visuals;
+        let line_stroke = egui::Stroke::new(1.0, visuals.widgets.noninteractive.bg_stroke.color);
+        let text_color = visuals.weak_text_color();
+        let font = egui::FontId::proportional(10.0);
+
+        painter.rect(
+            self.rect,
+            visuals.widgets.noninteractive.corner_radius,
+            visuals.extreme_bg_color,
+            visuals.widgets.noninteractive.bg_stroke,
+            egui::StrokeKind::Inside,
+        );
+
+        for frequency in frequency_grid_lines(self.frequency_range.clone()) {
+            let x = self.x_for_frequency(frequency);
+            painter.vline(x, self.rect.y_range(), line_stroke);
+            painter.text(
+                egui::pos2(x + 2.0, self.rect.bottom() - 1.0),
+                egui::Align2::LEFT_BOTTOM,
+                format_frequency(frequency),
+                font.clone(),
+                text_color,
+            );
+        }
+
+        let db_step = db_grid_step(self.db_range.clone(), self.rect.height());
+        let mut db = (self.db_range.start() / db_step).ceil() * db_step;
+        while db <= *self.db_range.end() {
+            let y = self.y_for_db(db);
+            painter.hline(self.rect.x_range(), y, line_stroke);
+            painter.text(
+                egui::pos2(self.rect.left() + 2.0, y),
+                egui::Align2::LEFT_BOTTOM,
+                format!("{db:.0}dB"),
+                font.clone(),
+                text_color,
+            );
+            db += db_step;
+        }
+    }
+}
+
+/// 1-2-5 steps per decade, e.g. 20, 50, 100, 200, 500, 1k, ...
+fn frequency_grid_lines(range: RangeInclusive<f32>) -> impl Iterator<Item = f32> {
+    let first_decade = range.start().max(1.0).log10().floor() as i32;
+    let last_decade = range.end().max(1.0).log10().ceil() as i32;
+
+    (first_decade..=last_decade)
+        .flat_map(|decade| [1.0, 2.0, 5.0].map(|m| m * 10.0f32.powi(decade)))
+        .filter(move |f| range.contains(f))
+}
+
+fn db_grid_step(range: RangeInclusive<f32>, height: f32) -> f32 {
+    let span = (range.end() - range.start()).abs();
+    let max_lines = (height / 24.0).max(1.0);
+    [1.0, 3.0, 6.0, 12.0, 24.0, 48.0]
+        .into_iter()
+        .find(|step| span / step <= max_lines)
+        .unwrap_or(96.0)
+}
+
+pub(crate) fn format_frequency(frequency: f32) -> String {
+    if frequency >= 1000.0 {
+        format!("{}k", frequency / 1000.0)
+    }
+    else {
+        format!("{frequency:.0}")
+    }
+}
+
+/// Fills the area between a polyline and a horizontal baseline.
+pub(crate) fn fill_to_baseline(
+    points: &[egui::Pos2],
+    baseline: f32,
+    color: egui::Color32,
+) -> egui::Mesh {
+    strip_mesh(
+        points
+            .iter()
+            .map(|p| [(*p, color), (egui::pos2(p.x, baseline), color)]),
+    )
+}
+
+/// Connects columns of `N` vertices into bands of triangles, joining every vertex to the one in
+/// the same row of the next column.
+pub(crate) fn strip_mesh<const N: usize>(
+    columns: impl IntoIterator<Item = [(egui::Pos2, egui::Color32); N]>,
+) -> egui::Mesh {
+    let mut mesh = egui::Mesh::default();
+    for (i, column) in columns.into_iter().enumerate() {
+        for (pos, color) in column {
+            mesh.colored_vertex(pos, color);
+        }
+        if i > 0 {
+            let current = (i * N) as u32;
+            let previous = current - N as u32;
+            for row in 0..N as u32 - 1 {
+                mesh.add_triangle(previous + row, previous + row + 1, current + row);
+                mesh.add_triangle(previous + row + 1, current + row, current + row + 1);
+            }
+        }
+    }
+    mesh
+}
@@ -0,0 +1,168 @@
+use std::ops::RangeInclusive;
+
+use crate::frequency_grid::{FrequencyGrid, fill_to_baseline};
+use crate::util::to_db_deadzone;
+
+pub enum ResponseSource<'a> {
+    /// `(frequency, linear magnitude)` pairs, sorted by frequency
+    Points(&'a [egui::Vec2]),
+    /// Maps a frequency to a linear magnitude
+    Function(Box<dyn Fn(f32) -> f32 + 'a>),
+}
+
+pub struct ResponseCurve<'a> {
+    pub source: ResponseSource<'a>,
+    pub stroke: Option<egui::Stroke>,
+    pub fill:   Option<egui::Color32>,
+}
+impl<'a> ResponseCurve<'a> {
+    pub fn from_points(points: &'a [egui::Vec2]) -> Self {
+        Self {
+            source: ResponseSource::Points(points),
+            stroke: None,
+            fill:   None,
+        }
+    }
+
+    pub fn from_fn(magnitude: impl Fn(f32) -> f32 + 'a) -> Self {
+        Self {
+            source: ResponseSource::Function(Box::new(magnitude)),
+            stroke: None,
+            fill:   None,
+        }
+    }
+
+    /// `response` maps a frequency to a complex value `[re, im]`
+    pub fn from_complex_fn(response: impl Fn(f32) -> [f32; 2] + 'a) -> Self {
+        Self::from_fn(move |frequency| {
+            let [re, im] = response(frequency);
+            re.hypot(im)
+        })
+    }
+
+    pub fn with_stroke(self, stroke: impl Into<egui::Stroke>) -> Self {
+        Self {
+            stroke: Some(stroke.into()),
+            ..self
+        }
+    }
+
+    pub fn with_fill(self, fill: impl Into<egui::Color32>) -> Self {
+        Self {
+            fill: Some(fill.into()),
+            ..self
+        }
+    }
+
+    pub(crate) fn screen_points(
+        &self,
+        grid: &FrequencyGrid,
+        pixels_per_point: f32,
+    ) -> Vec<egui::Pos2> {
+        let floor_db = *grid.db_range.start();
+        let to_pos = |frequency: f32, magnitude: f32| {
+            grid.pos(frequency, to_db_deadzone(magnitude, floor_db).max(floor_db))
+        };
+
+        match &self.source {
+            ResponseSource::Points(points) => points.iter().map(|p| to_pos(p.x, p.y)).collect(),
+            ResponseSource::Function(magnitude) => {
+                let num_points = (grid.rect.width() / pixels_per_point).ceil() as usize + 1;
+                (0..num_points)
+                    .map(|i| {
+                        let x =
+                            (grid.rect.left() + i as f32 * pixels_per_point).min(grid.rect.right());
+                        let frequency = grid.frequency_for_x(x);
+                        to_pos(frequency, magnitude(frequency))
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+pub struct FrequencyResponse<'a> {
+    pub curves: Vec<ResponseCurve<'a>>,
+    pub frequency_range: RangeInclusive<f32>,
+    pub db_range: RangeInclusive<f32>,
+    pub pixels_per_point: f32,
+    pub height: f32,
+}
+impl<'a> Default for FrequencyResponse<'a> {
+    fn default() -> Self {
+        Self {
+            curves: Vec::new(),
+            frequency_range: 20.0..=20000.0,
+            db_range: -24.0..=24.0,
+            pixels_per_point: 2.0,
+            height: 200.0,
+        }
+    }
+}
+impl<'a> FrequencyResponse<'a> {
+    pub fn from_points(points: &'a [egui::Vec2]) -> Self {
+        Self::default().curve(ResponseCurve::from_points(points))
+    }
+
+    pub fn from_fn(magnitude: impl Fn(f32) -> f32 + 'a) -> Self {
+        Self::default().curve(ResponseCurve::from_fn(magnitude))
+    }
+
+    pub fn curve(mut self, curve: ResponseCurve<'a>) -> Self {
+        self.curves.push(curve);
+        self
+    }
+
+    pub fn frequency_range(self, frequency_range: RangeInclusive<f32>) -> Self {
+        Self {
+            frequency_range,
+            ..self
+        }
+    }
+
+    pub fn db_range(self, db_range: RangeInclusive<f32>) -> Self { Self { db_range, ..self } }
+
+    pub fn pixels_per_point(self, pixels_per_point: f32) -> Self {
+        Self {
+            pixels_per_point,
+            ..self
+        }
+    }
+
+    pub fn height(self, height: f32) -> Self { Self { height, ..self } }
+
+    pub(crate) fn grid(&self, rect: egui::Rect) -> FrequencyGrid {
+        FrequencyGrid::new(rect, self.frequency_range.clone(), self.db_range.clone())
+    }
+
+    pub(crate) fn paint(&self, ui: &egui::Ui, grid: &FrequencyGrid) {
+        let painter = ui.painter_at(grid.rect);
+        grid.paint(&painter, ui.style());
+
+        let baseline = grid.y_for_db(0.0f32.clamp(*grid.db_range.start(), *grid.db_range.end()));
+
+        for curve in &self.curves {
+            let points = curve.screen_points(grid, self.pixels_per_point);
+            let stroke = curve
+                .stroke
+                .unwrap_or(ui.style().visuals.widgets.noninteractive.fg_stroke);
+
+            if let Some(fill) = curve.fill {
+                painter.add(fill_to_baseline(&points, baseline, fill));
+            }
+            painter.add(egui::Shape::line(points, stroke));
+        }
+    }
+}
+impl<'a> egui::Widget for FrequencyResponse<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let (rect, response) = ui.allocate_at_least(
+            egui::vec2(ui.available_width(), self.height),
+            egui::Sense::hover(),
+        );
+
+        self.paint(ui, &self.grid(rect));
+
+        response
+    }
+}
@@ -0,0 +1,205 @@
+use std::ops::RangeInclusive;
+
+use egui::Vec2;
+
+use crate::util::{from_db, to_db_deadzone};
+
+/// Peak hold and clip indicator of a [`LevelMeter`] that have to persist between frames.
+#[derive(Debug, Clone, Default)]
+pub struct LevelMeterState {
+    pub peak_hold: f32,
+    pub peak_hold_age: f32,
+    pub clipped: bool,
+}
+impl LevelMeterState {
+    pub fn reset_clip(&mut self) { self.clipped = false; }
+
+    fn update(&mut self, peak: f32, dt: f32, hold_time: f32, fall_off: f32, clip_level: f32) {
+        if peak >= clip_level {
+            self.clipped = true;
+        }
+
+        if peak >= self.peak_hold {
+            self.peak_hold = peak;
+            self.peak_hold_age = 0.0;
+        }
+        else {
+            self.peak_hold_age += dt;
+            if self.peak_hold_age > hold_time {
+                let fallen = from_db(to_db_deadzone(self.peak_hold, -200.0) - fall_off * dt);
+                self.peak_hold = f32::max(peak, fallen);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LevelMeter<'a> {
+    rms: f32,
+    peak: f32,
+    state: Option<&'a mut LevelMeterState>,
+    range: RangeInclusive<f32>,
+    size: Vec2,
+    hold_time: f32,
+    fall_off: f32,
+    clip_level: f32,
+    show_scale: bool,
+}
+impl<'a> LevelMeter<'a> {
+    /// `rms` and `peak` are linear amplitudes
+    pub fn new(rms: f32, peak: f32) -> Self {
+        Self {
+            rms,
+            peak,
+            state: None,
+            range: -60.0..=0.0,
+            size: Vec2::new(20.0, 150.0),
+            hold_time: 1.5,
+            fall_off: 12.0,
+            clip_level: 1.0,
+            show_scale: false,
+        }
+    }
+
+    pub fn state(self, state: &'a mut LevelMeterState) -> Self {
+        Self {
+            state: Some(state),
+            ..self
+        }
+    }
+
+    /// Displayed range in dB, everything below the start is treated as silence
+    pub fn range(self, range: RangeInclusive<f32>) -> Self { Self { range, ..self } }
+
+    pub fn size(self, size: Vec2) -> Self { Self { size, ..self } }
+
+    /// Seconds the peak hold line stays before it starts falling
+    pub fn hold_time(self, hold_time: f32) -> Self { Self { hold_time, ..self } }
+
+    /// Fall speed of the peak hold line in dB per second
+    pub fn fall_off(self, fall_off: f32) -> Self { Self { fall_off, ..self } }
+
+    /// Linear amplitude at which the clip indicator latches
+    pub fn clip_level(self, clip_level: f32) -> Self { Self { clip_level, ..self } }
+
+    pub fn show_scale(self, show_scale: bool) -> Self { Self { show_scale, ..self } }
+
+    /// Updates the state and draws the meter bar into `rect` and the clip indicator into
+    /// `led_rect`. Used by [`crate::Fader`] to draw the meter inside its track.
+    pub(crate) fn paint(
+        self,
+        ui: &egui::Ui,
+        rect: egui::Rect,
+        led_rect: egui::Rect,
+        reset_clip: bool,
+    ) {
+        let dt = ui.input(|i| i.stable_dt).min(0.1);
+        let mut fallback_state = LevelMeterState::default();
+        let has_state = self.state.is_some();
+        let state = self.state.unwrap_or(&mut fallback_state);
+
+        if reset_clip {
+            state.reset_clip();
+        }
+        state.update(
+            self.peak,
+            dt,
+            self.hold_time,
+            self.fall_off,
+            self.clip_level,
+        );
+        if has_state && state.peak_hold > 0.0 {
+            ui.ctx().request_repaint();
+        }
+
+        let visuals = &ui.style().visuals;
+        let painter = ui.painter_at(rect.union(led_rect).expand(1.0));
+        let bar_rect = if self.show_scale {
+            rect.with_max_x(rect.left() + rect.width() * 0.5)
+        }
+        else {
+            rect
+        };
+
+        let floor_db = *self.range.start();
+        let to_y = |amplitude: f32| {
+            egui::remap_clamp(
+                to_db_deadzone(amplitude, floor_db),
+                self.range.clone(),
+                bar_rect.bottom()..=bar_rect.top(),
+            )
+        };
+
+        painter.rect_filled(
+            led_rect,
+            1.0,
+            if state.clipped {
+                visuals.error_fg_color
+            }
+            else {
+                visuals.extreme_bg_color
+            },
+        );
+        painter.rect_filled(bar_rect, 1.0, visuals.extreme_bg_color);
+
+        // Green / yellow / red zones, clipped to the current level
+        let zones = [
+            (floor_db, -18.0, egui::Color32::from_rgb(0x3c, 0xb3, 0x71)),
+            (-18.0, -6.0, visuals.warn_fg_color),
+            (-6.0, f32::INFINITY, visuals.error_fg_color),
+        ];
+        for (level, alpha) in [(self.peak, 0.4), (self.rms, 1.0)] {
+            let level_y = to_y(level);
+            for (zone_start, zone_end, color) in zones {
+                let zone_bottom = to_y(from_db(zone_start));
+                let zone_top = to_y(from_db(zone_end)).max(level_y);
+                if zone_top < zone_bottom {
+                    painter.rect_filled(
+                        egui::Rect::from_x_y_ranges(bar_rect.x_range(), zone_top..=zone_bottom),
+                        0.0,
+                        color.gamma_multiply(alpha),
+                    );
+                }
+            }
+        }
+
+        if state.peak_hold > 0.0 {
+            painter.hline(
+                bar_rect.x_range(),
+                to_y(state.peak_hold),
+                (1.0, visuals.strong_text_color()),
+            );
+        }
+
+        if self.show_scale {
+            let mut db = *self.range.end();
+            while db >= floor_db {
+                let y = to_y(from_db(db));
+                painter.hline(
+                    bar_rect.right()..=bar_rect.right() + 3.0,
+                    y,
+                    (1.0, visuals.weak_text_color()),
+                );
+                painter.text(
+                    egui::pos2(rect.right(), y),
+                    egui::Align2::RIGHT_CENTER,
+                    format!("{db:.0}"),
+                    egui::FontId::proportional(9.0),
+                    visuals.weak_text_color(),
+                );
+                db -= 6.0;
+            }
+        }
+    }
+}
+impl<'a> egui::Widget for LevelMeter<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let (rect, res) = ui.allocate_at_least(self.size, egui::Sense::click());
+
+        let led_height = (rect.width() * 0.5).min(8.0);
+        let led_rect = egui::Rect::from_min_size(rect.min, egui::vec2(rect.width(), led_height));
+        let bar_rect = rect.with_min_y(led_rect.bottom() + 2.0);
+        self.paint(ui, bar_rect, led_rect, res.clicked());
+        res
+    }
+}
@@ -1,6 +1,8 @@
 #![allow(dead_code)]
 
 mod fader;
+mod fft;
+mod frequency_grid;
 mod knob;
 mod util;
 
@@ -8,18 +10,34 @@ mod util;
 mod atomic_wrapper;
 
 mod as_scaled;
+mod automation_lane;
 mod db_wrapper;
 mod envelope;
+mod frequency_response;
+mod level_meter;
+mod parametric_eq;
+mod sample;
+mod spectrum;
+mod tempo_map;
 mod time_cursor;
+mod time_ruler;
 mod waveform;
 
 pub use as_scaled::*;
 #[cfg(feature = "atomic-traits")]
 pub use atomic_wrapper::*;
+pub use automation_lane::*;
 pub use db_wrapper::*;
 pub use envelope::*;
 pub use fader::*;
+pub use frequency_response::*;
 pub use knob::*;
+pub use level_meter::*;
+pub use parametric_eq::*;
+pub use sample::*;
+pub use spectrum::*;
+pub use tempo_map::*;
 pub use time_cursor::*;
+pub use time_ruler::*;
 pub use util::*;
 pub use waveform::*;
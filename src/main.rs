@@ -1,9 +1,10 @@
 use std::f32::consts::TAU;
 
-use egui_audio::{Fader, Knob};
+use egui_audio::{Fader, Knob, LevelMeter, LevelMeterState};
 
 fn main() {
     let mut faders = [(0f32, 0f32); 8];
+    let mut meters: [LevelMeterState; 8] = Default::default();
 
     let mut control_points = vec![
         egui_audio::ControlPoint::new(egui::vec2(1.0, 0.0)),
@@ -11,63 +12,350 @@ fn main() {
         egui_audio::ControlPoint::new(egui::vec2(0.0, 1.0)),
     ];
 
-    let waveform =
-        egui_audio::WaveformShape::generate(&generate_example_waveform(48000, 10.0), 48000, 2, 2);
+    // Opens the WAV file passed as the first argument, or falls back to a generated waveform
+    let (samples, sample_rate, waveform) = match std::env::args().nth(1) {
+        Some(path) => load_wav(&path),
+        None => {
+            let samples = generate_example_waveform(48000, 10.0);
+            let waveform = egui_audio::WaveformShape::generate(&samples, 48000, 2, 2);
+            (samples, 48000, waveform)
+        }
+    };
+    let spectrogram = egui_audio::WaveformSpectrogram::generate(&samples, sample_rate, 1024, 256);
+    let mut display = egui_audio::WaveformDisplay::default();
+    let mut spectrogram_style = egui_audio::SpectrogramStyle::default();
+    let mut eq_bands = vec![
+        egui_audio::EqBand::high_pass(40.0, 0.7),
+        egui_audio::EqBand::low_shelf(120.0, 3.0),
+        egui_audio::EqBand::peaking(1000.0, -6.0, 2.0),
+        egui_audio::EqBand::high_shelf(8000.0, 2.0),
+    ];
+    let mut eq_selected = None;
+    let mut spectrum_state = egui_audio::SpectrumState::default();
     let mut cursor = egui_audio::TimeCursor::default();
-    let mut waveform_offset = 0.0;
+    let mut item_placement = egui_audio::WaveformItemChanged {
+        index: 0,
+        position: 0.0,
+        source_offset: 0.0,
+        length: waveform.len_seconds(),
+        lane: 0,
+        fade_in: 0.5,
+        fade_out: 0.5,
+    };
+    let mut fade_curve = egui_audio::FadeCurve::EqualPower;
+    let mut gain_envelope = vec![
+        egui_audio::GainPoint::new(3.0, 1.0),
+        egui_audio::GainPoint::new(4.0, 0.3),
+        egui_audio::GainPoint::new(5.0, 1.0),
+    ];
+    let mut multitrack_cursor = egui_audio::TimeCursor::default();
+    let mut lanes = vec![
+        egui_audio::WaveformLane::new("Drums").with_height(60.0),
+        egui_audio::WaveformLane::new("Bass").with_height(60.0),
+        egui_audio::WaveformLane::new("Vocals").with_height(60.0),
+    ];
+    let mut clips: Vec<_> = [(0.0, 0), (2.0, 1), (6.0, 1), (1.0, 2)]
+        .into_iter()
+        .enumerate()
+        .map(
+            |(index, (position, lane))| egui_audio::WaveformItemChanged {
+                index,
+                position,
+                source_offset: position,
+                length: 3.0,
+                lane,
+                fade_in: 0.2,
+                fade_out: 0.2,
+            },
+        )
+        .collect();
+    let mut automation = vec![
+        egui_audio::AutomationPoint::new(0.0, 200.0),
+        egui_audio::AutomationPoint::new(2.0, 4000.0)
+            .with_segment(egui_audio::AutomationSegment::Curve { tension: 0.5 }),
+        egui_audio::AutomationPoint::new(5.0, 800.0)
+            .with_segment(egui_audio::AutomationSegment::Step),
+        egui_audio::AutomationPoint::new(7.0, 2000.0),
+    ];
+    let mut playback_started = 0.0;
+    let mut recording = egui_audio::WaveformShapeBuilder::new(1, sample_rate, 2, 2);
+    let mut recording_cursor = egui_audio::TimeCursor::default();
+    let mut loading_job: Option<egui_audio::WaveformJob> = None;
+    let mut loading_cursor = egui_audio::TimeCursor::default();
+    let tempo_map = egui_audio::TempoMap::new(100.0, 4, 4);
+    let mut show_beat_grid = false;
+    let mut show_rms = true;
+    let mut db_scale = false;
+    let mut selection = None;
+    let mut markers = vec![
+        egui_audio::WaveformMarker::from_range(0.0..1.0)
+            .with_label("Red Marker")
+            .with_color(egui::Color32::RED),
+        egui_audio::WaveformMarker::from_range(5.0..6.0)
+            .with_label("Yellow Marker")
+            .with_color(egui::Color32::YELLOW),
+        egui_audio::WaveformMarker::from_range(2.0..2.1)
+            .with_label("Blue Marker")
+            .with_color(egui::Color32::BLUE),
+        egui_audio::WaveformMarker::from_range(8.0..8.3)
+            .with_label("Green Marker")
+            .with_color(egui::Color32::GREEN),
+    ];
 
     eframe::run_ui_native("audio_demo", Default::default(), move |ui, _frame| {
         egui::CentralPanel::default().show_inside(ui, |ui| {
             egui::ScrollArea::vertical().show(ui, |ui| {
                 ui.heading("Faders / Knobs");
+                let time = ui.input(|i| i.time);
+                let playhead = (time - playback_started).rem_euclid(waveform.len_seconds() as f64);
+                let playback_position = (playhead * sample_rate as f64) as usize;
+                let (rms, peak) = measure(&samples[playback_position..], 2048);
                 ui.horizontal(|ui| {
-                    for (volume, _pan) in &mut faders {
+                    for ((volume, _pan), meter) in faders.iter_mut().zip(&mut meters) {
+                        let (rms, peak) = (rms * *volume, peak * *volume);
                         ui.vertical(|ui| {
                             ui.add(Knob::pan(_pan).label("pan"));
-                            ui.add(Fader::volume(volume).range(-32.0..=0.0).label("volume"));
+                            ui.add(
+                                Fader::volume(volume)
+                                    .range(-32.0..=0.0)
+                                    .label("volume")
+                                    .meter(LevelMeter::new(rms, peak).state(meter)),
+                            );
                         });
                     }
                 });
                 ui.heading("Envelope");
                 ui.add(egui_audio::Envelope::new(&mut control_points));
                 ui.heading("Waveform");
-                ui.label("offset");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut show_beat_grid, "Beat grid");
+                    ui.checkbox(&mut show_rms, "RMS");
+                    ui.checkbox(&mut db_scale, "dB scale");
+                    egui::ComboBox::from_label("Fade curve")
+                        .selected_text(format!("{fade_curve:?}"))
+                        .show_ui(ui, |ui| {
+                            for curve in [
+                                egui_audio::FadeCurve::Linear,
+                                egui_audio::FadeCurve::EqualPower,
+                                egui_audio::FadeCurve::Exponential,
+                                egui_audio::FadeCurve::SCurve,
+                            ] {
+                                ui.selectable_value(&mut fade_curve, curve, format!("{curve:?}"));
+                            }
+                        });
+                    egui::ComboBox::from_label("Display")
+                        .selected_text(format!("{display:?}"))
+                        .show_ui(ui, |ui| {
+                            for option in [
+                                egui_audio::WaveformDisplay::Outline,
+                                egui_audio::WaveformDisplay::Spectrogram,
+                                egui_audio::WaveformDisplay::SpectrogramAndOutline,
+                            ] {
+                                ui.selectable_value(&mut display, option, format!("{option:?}"));
+                            }
+                        });
+                    egui::ComboBox::from_label("Colormap")
+                        .selected_text(format!("{:?}", spectrogram_style.colormap))
+                        .show_ui(ui, |ui| {
+                            for colormap in [
+                                egui_audio::Colormap::Magma,
+                                egui_audio::Colormap::Viridis,
+                                egui_audio::Colormap::Grayscale,
+                            ] {
+                                ui.selectable_value(
+                                    &mut spectrogram_style.colormap,
+                                    colormap,
+                                    format!("{colormap:?}"),
+                                );
+                            }
+                        });
+                    let mut logarithmic = spectrogram_style.frequency_scale
+                        == egui_audio::FrequencyScale::Logarithmic;
+                    if ui.checkbox(&mut logarithmic, "Log frequency").changed() {
+                        spectrogram_style.frequency_scale = if logarithmic {
+                            egui_audio::FrequencyScale::Logarithmic
+                        }
+                        else {
+                            egui_audio::FrequencyScale::Linear
+                        };
+                    }
+                });
+                let mut snap = egui_audio::WaveformSnap::default()
+                    .with_markers(true)
+                    .with_items(true)
+                    .with_zero_crossings(true);
+                let mut waveform_widget = egui_audio::Waveform::default();
+                if show_beat_grid {
+                    waveform_widget = waveform_widget.tempo_map(&tempo_map);
+                    snap = snap.with_grid(egui_audio::SnapGrid::Beats(1));
+                }
+                let waveform_response = waveform_widget
+                    .entry(
+                        egui_audio::WaveformItem::new(&waveform)
+                            .with_position(item_placement.position)
+                            .with_source_offset(item_placement.source_offset)
+                            .with_length(item_placement.length)
+                            .with_fade_in(item_placement.fade_in, fade_curve)
+                            .with_fade_out(item_placement.fade_out, fade_curve)
+                            .with_envelope(&gain_envelope)
+                            .with_spectrogram(&spectrogram)
+                            .with_fill(
+                                egui_audio::WaveformFill::new(egui::Color32::from_rgb(40, 80, 140))
+                                    .with_gradient(egui::Color32::from_rgb(120, 190, 255)),
+                            ),
+                    )
+                    .display(display)
+                    .ruler_height(18.0)
+                    .spectrogram_style(spectrogram_style.clone())
+                    .editable_items(true)
+                    .editable_envelopes(true)
+                    .rms(show_rms)
+                    .amplitude_scale(if db_scale {
+                        egui_audio::AmplitudeScale::Decibels { floor: -60.0 }
+                    }
+                    else {
+                        egui_audio::AmplitudeScale::Linear
+                    })
+                    .amplitude_grid(db_scale)
+                    .editable_markers(&mut markers)
+                    .cursor(&mut cursor)
+                    .playhead(playhead as f32)
+                    .follow_playhead(egui_audio::PlayheadFollow::Page)
+                    .snap(snap)
+                    .selection(&mut selection)
+                    .show(ui);
+                let timecode_response = egui_audio::TimeRuler::new(&mut cursor)
+                    .format(egui_audio::TimeFormat::Timecode { fps: 30.0 })
+                    .show(ui);
                 ui.add(
-                    egui::DragValue::new(&mut waveform_offset)
-                        .range(-waveform.len_seconds()..=waveform.len_seconds())
-                        .speed(0.01)
-                        .suffix("s"),
+                    egui_audio::AutomationLane::new(&mut automation)
+                        .cursor(&mut cursor)
+                        .range(20.0..=20000.0)
+                        .unit(egui_audio::AutomationUnit::Hertz),
                 );
+                if let Some(changed) = waveform_response.inner.item_changed {
+                    item_placement = changed;
+                }
+                if let Some(edit) = waveform_response.inner.envelope_edit {
+                    edit.apply(&mut gain_envelope);
+                }
+                if let Some(seek) = waveform_response.inner.seek.or(timecode_response.inner) {
+                    playback_started = time - seek as f64;
+                }
+                ui.heading("Multitrack");
+                let multitrack_response = egui_audio::Waveform::default()
+                    .entries(clips.iter().map(|clip| {
+                        egui_audio::WaveformItem::new(&waveform)
+                            .with_position(clip.position)
+                            .with_source_offset(clip.source_offset)
+                            .with_length(clip.length)
+                            .with_lane(clip.lane)
+                            .with_fill(egui_audio::WaveformFill::new(egui::Color32::from_rgb(
+                                60, 120, 90,
+                            )))
+                            .with_fade_in(clip.fade_in, egui_audio::FadeCurve::default())
+                            .with_fade_out(clip.fade_out, egui_audio::FadeCurve::default())
+                    }))
+                    .lanes(&mut lanes)
+                    .ruler_height(18.0)
+                    .editable_items(true)
+                    .cursor(&mut multitrack_cursor)
+                    .height(150.0)
+                    .show(ui);
+                if let Some(changed) = multitrack_response.inner.item_changed {
+                    clips[changed.index] = changed;
+                }
+                ui.heading("Recording");
+                // Records the playing samples, starting over when playback wraps around
+                let recorded = recording.shape().num_samples;
+                if playback_position < recorded {
+                    recording = egui_audio::WaveformShapeBuilder::new(1, sample_rate, 2, 2);
+                }
+                let recorded = recording.shape().num_samples;
+                recording.push(&samples[recorded..playback_position.max(recorded)]);
                 egui_audio::Waveform::default()
-                    .entry(egui_audio::WaveformItem::new(&waveform).with_position(waveform_offset))
-                    .marker(
-                        egui_audio::WaveformMarker::from_range(0.0..1.0)
-                            .with_label("Red Marker")
-                            .with_color(egui::Color32::RED),
-                    )
-                    .marker(
-                        egui_audio::WaveformMarker::from_range(5.0..6.0)
-                            .with_label("Yellow Marker")
-                            .with_color(egui::Color32::YELLOW),
-                    )
-                    .marker(
-                        egui_audio::WaveformMarker::from_range(2.0..2.1)
-                            .with_label("Blue Marker")
-                            .with_color(egui::Color32::BLUE),
-                    )
-                    .marker(
-                        egui_audio::WaveformMarker::from_range(8.0..8.3)
-                            .with_label("Green Marker")
-                            .with_color(egui::Color32::GREEN),
-                    )
-                    .cursor(&mut cursor)
+                    .entry(egui_audio::WaveformItem::new(recording.shape()))
+                    .cursor(&mut recording_cursor)
+                    .height(100.0)
                     .show(ui);
+                ui.heading("Background loading");
+                ui.horizontal(|ui| {
+                    if ui.button("Load").clicked() {
+                        loading_job = Some(egui_audio::WaveformJob::spawn(
+                            generate_example_waveform(48000, 120.0),
+                            48000,
+                            2,
+                            2,
+                            Some(ui.ctx().clone()),
+                        ));
+                    }
+                    if ui.button("Close").clicked() {
+                        // Dropping the job cancels it
+                        loading_job = None;
+                    }
+                });
+                if let Some(job) = &loading_job {
+                    let shape = job.shape();
+                    let mut item = egui_audio::WaveformItem::new(&shape);
+                    if !job.is_finished() {
+                        item = item.with_loading(job.progress());
+                    }
+                    egui_audio::Waveform::default()
+                        .entry(item)
+                        .cursor(&mut loading_cursor)
+                        .height(100.0)
+                        .show(ui);
+                }
+                ui.heading("Frequency response");
+                ui.add(egui_audio::FrequencyResponse::from_fn(|f| {
+                    // One pole low pass at 1kHz
+                    1.0 / (1.0 + (f / 1000.0).powi(2)).sqrt()
+                }));
+                ui.heading("Parametric EQ");
+                ui.add(
+                    egui_audio::ParametricEq::new(&mut eq_bands, 48000.0)
+                        .selected(&mut eq_selected),
+                );
+                ui.heading("Spectrum");
+                ui.add(
+                    egui_audio::Spectrum::from_samples(
+                        &samples[..4096.min(samples.len())],
+                        sample_rate as f32,
+                    )
+                    .state(&mut spectrum_state),
+                );
             });
         });
     })
     .expect("Failed to open window");
 }
 
+/// Reads a WAV file into a waveform of all channels, plus the samples of the first channel for
+/// the meters and spectrum
+fn load_wav(path: &str) -> (Vec<f32>, usize, egui_audio::WaveformShape) {
+    let mut chunks =
+        egui_audio::WavChunkReader::open(path, 1 << 16).expect("Failed to open WAV file");
+    let num_channels = chunks.num_channels();
+    let sample_rate = chunks.sample_rate();
+
+    let mut samples = Vec::with_capacity(chunks.num_frames());
+    let mut builder = egui_audio::WaveformShapeBuilder::new(num_channels, sample_rate, 2, 2);
+    while let Some(chunk) = chunks.next_chunk() {
+        let chunk = chunk.expect("Failed to read WAV file");
+        samples.extend(chunk.iter().step_by(num_channels));
+        builder.push_interleaved(chunk);
+    }
+
+    (samples, sample_rate, builder.finish())
+}
+
+fn measure(samples: &[f32], window: usize) -> (f32, f32) {
+    let samples = &samples[..window.min(samples.len())];
+    let sum_of_squares: f32 = samples.iter().map(|s| s * s).sum();
+    let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    ((sum_of_squares / samples.len().max(1) as f32).sqrt(), peak)
+}
+
 pub fn generate_example_waveform(sample_rate: usize, seconds: f32) -> Vec<f32> {
     let length = (seconds * sample_rate as f32).ceil() as usize;
 
@@ -0,0 +1,383 @@
+use std::f64::consts::TAU;
+use std::mem::take;
+use std::ops::RangeInclusive;
+
+use crate::frequency_grid::FrequencyGrid;
+use crate::{FrequencyResponse, ResponseCurve};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EqBandType {
+    Peaking,
+    LowShelf,
+    HighShelf,
+    LowPass,
+    HighPass,
+    Notch,
+}
+impl EqBandType {
+    pub fn has_gain(self) -> bool {
+        matches!(self, Self::Peaking | Self::LowShelf | Self::HighShelf)
+    }
+}
+
+/// Normalized biquad coefficients (`a0 == 1`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiquadCoefficients {
+    pub b0: f64,
+    pub b1: f64,
+    pub b2: f64,
+    pub a1: f64,
+    pub a2: f64,
+}
+impl BiquadCoefficients {
+    pub fn magnitude(&self, frequency: f32, sample_rate: f32) -> f32 {
+        let w = TAU * frequency as f64 / sample_rate as f64;
+        let (sin1, cos1) = w.sin_cos();
+        let (sin2, cos2) = (2.0 * w).sin_cos();
+
+        let num_re = self.b0 + self.b1 * cos1 + self.b2 * cos2;
+        let num_im = -(self.b1 * sin1 + self.b2 * sin2);
+        let den_re = 1.0 + self.a1 * cos1 + self.a2 * cos2;
+        let den_im = -(self.a1 * sin1 + self.a2 * sin2);
+
+        (num_re.hypot(num_im) / den_re.hypot(den_im)) as f32
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EqBand {
+    pub band_type: EqBandType,
+    pub frequency: f32,
+    pub gain_db: f32,
+    pub q: f32,
+    pub enabled: bool,
+    pub default: Option<Box<EqBand>>,
+}
+impl EqBand {
+    pub fn new(band_type: EqBandType, frequency: f32, gain_db: f32, q: f32) -> Self {
+        let mut band = Self {
+            band_type,
+            frequency,
+            gain_db,
+            q,
+            enabled: true,
+            default: None,
+        };
+        band.default = Some(Box::new(band.clone()));
+        band
+    }
+
+    pub fn peaking(frequency: f32, gain_db: f32, q: f32) -> Self {
+        Self::new(EqBandType::Peaking, frequency, gain_db, q)
+    }
+
+    pub fn low_shelf(frequency: f32, gain_db: f32) -> Self {
+        Self::new(
+            EqBandType::LowShelf,
+            frequency,
+            gain_db,
+            std::f32::consts::FRAC_1_SQRT_2,
+        )
+    }
+
+    pub fn high_shelf(frequency: f32, gain_db: f32) -> Self {
+        Self::new(
+            EqBandType::HighShelf,
+            frequency,
+            gain_db,
+            std::f32::consts::FRAC_1_SQRT_2,
+        )
+    }
+
+    pub fn low_pass(frequency: f32, q: f32) -> Self {
+        Self::new(EqBandType::LowPass, frequency, 0.0, q)
+    }
+
+    pub fn high_pass(frequency: f32, q: f32) -> Self {
+        Self::new(EqBandType::HighPass, frequency, 0.0, q)
+    }
+
+    pub fn notch(frequency: f32, q: f32) -> Self { Self::new(EqBandType::Notch, frequency, 0.0, q) }
+
+    pub fn reset(&mut self) {
+        if let Some(default) = &self.default {
+            self.band_type = default.band_type;
+            self.frequency = default.frequency;
+            self.gain_db = default.gain_db;
+            self.q = default.q;
+            self.enabled = default.enabled;
+        }
+    }
+
+    /// RBJ audio EQ cookbook coefficients
+    pub fn coefficients(&self, sample_rate: f32) -> BiquadCoefficients {
+        let sample_rate = sample_rate as f64;
+        let frequency = (self.frequency as f64).clamp(1.0, sample_rate * 0.499);
+        let w0 = TAU * frequency / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * self.q.max(0.01) as f64);
+        let a = 10.0f64.powf(self.gain_db as f64 / 40.0);
+        let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        let [b0, b1, b2, a0, a1, a2] = match self.band_type {
+            EqBandType::Peaking => [
+                1.0 + alpha * a,
+                -2.0 * cos,
+                1.0 - alpha * a,
+                1.0 + alpha / a,
+                -2.0 * cos,
+                1.0 - alpha / a,
+            ],
+            EqBandType::LowShelf => [
+                a * ((a + 1.0) - (a - 1.0) * cos + sqrt_a_alpha),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - sqrt_a_alpha),
+                (a + 1.0) + (a - 1.0) * cos + sqrt_a_alpha,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - sqrt_a_alpha,
+            ],
+            EqBandType::HighShelf => [
+                a * ((a + 1.0) + (a - 1.0) * cos + sqrt_a_alpha),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                a * ((a + 1.0) + (a - 1.0) * cos - sqrt_a_alpha),
+                (a + 1.0) - (a - 1.0) * cos + sqrt_a_alpha,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - sqrt_a_alpha,
+            ],
+            EqBandType::LowPass => [
+                (1.0 - cos) / 2.0,
+                1.0 - cos,
+                (1.0 - cos) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos,
+                1.0 - alpha,
+            ],
+            EqBandType::HighPass => [
+                (1.0 + cos) / 2.0,
+                -(1.0 + cos),
+                (1.0 + cos) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos,
+                1.0 - alpha,
+            ],
+            EqBandType::Notch => [1.0, -2.0 * cos, 1.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        };
+
+        BiquadCoefficients {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+
+    pub fn magnitude(&self, frequency: f32, sample_rate: f32) -> f32 {
+        if !self.enabled {
+            return 1.0;
+        }
+        self.coefficients(sample_rate)
+            .magnitude(frequency, sample_rate)
+    }
+
+    fn handle_db(&self) -> f32 {
+        if self.band_type.has_gain() {
+            self.gain_db
+        }
+        else {
+            0.0
+        }
+    }
+}
+
+/// Combined magnitude response of all enabled bands
+pub fn eq_magnitude(bands: &[EqBand], frequency: f32, sample_rate: f32) -> f32 {
+    bands
+        .iter()
+        .map(|band| band.magnitude(frequency, sample_rate))
+        .product()
+}
+
+pub struct ParametricEq<'a> {
+    bands: &'a mut Vec<EqBand>,
+    selected: Option<&'a mut Option<usize>>,
+    sample_rate: f32,
+    frequency_range: RangeInclusive<f32>,
+    db_range: RangeInclusive<f32>,
+    q_range: RangeInclusive<f32>,
+    height: f32,
+    show_band_curves: bool,
+}
+impl<'a> ParametricEq<'a> {
+    pub fn new(bands: &'a mut Vec<EqBand>, sample_rate: f32) -> Self {
+        Self {
+            bands,
+            selected: None,
+            sample_rate,
+            frequency_range: 20.0..=20000.0,
+            db_range: -24.0..=24.0,
+            q_range: 0.1..=18.0,
+            height: 200.0,
+            show_band_curves: true,
+        }
+    }
+
+    pub fn selected(self, selected: &'a mut Option<usize>) -> Self {
+        Self {
+            selected: Some(selected),
+            ..self
+        }
+    }
+
+    pub fn frequency_range(self, frequency_range: RangeInclusive<f32>) -> Self {
+        Self {
+            frequency_range,
+            ..self
+        }
+    }
+
+    pub fn db_range(self, db_range: RangeInclusive<f32>) -> Self { Self { db_range, ..self } }
+
+    pub fn q_range(self, q_range: RangeInclusive<f32>) -> Self { Self { q_range, ..self } }
+
+    pub fn height(self, height: f32) -> Self { Self { height, ..self } }
+
+    pub fn show_band_curves(self, show_band_curves: bool) -> Self {
+        Self {
+            show_band_curves,
+            ..self
+        }
+    }
+}
+impl<'a> egui::Widget for ParametricEq<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let (rect, mut res) = ui.allocate_at_least(
+            egui::vec2(ui.available_width(), self.height),
+            egui::Sense::click_and_drag(),
+        );
+        let grid = FrequencyGrid::new(rect, self.frequency_range.clone(), self.db_range.clone());
+        let handle_radius = 6.0;
+
+        let mut fallback_selected = None;
+        let selected = self.selected.unwrap_or(&mut fallback_selected);
+        if selected.is_some_and(|i| i >= self.bands.len()) {
+            *selected = None;
+        }
+
+        let handle_pos = |band: &EqBand| grid.pos(band.frequency, band.handle_db());
+
+        let hovered = res.hover_pos().and_then(|pointer| {
+            self.bands
+                .iter()
+                .enumerate()
+                .map(|(i, band)| (i, (handle_pos(band) - pointer).length()))
+                .filter(|(_, distance)| *distance < handle_radius * 2.0)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(i, _)| i)
+        });
+
+        // Handle input
+        if res.drag_started() || res.clicked() {
+            *selected = hovered;
+        }
+
+        if res.double_clicked()
+            && let Some(i) = hovered
+        {
+            self.bands[i].reset();
+            res.mark_changed();
+        }
+        else if res.secondary_clicked()
+            && let Some(i) = hovered
+        {
+            self.bands[i].enabled = !self.bands[i].enabled;
+            res.mark_changed();
+        }
+        else if res.dragged_by(egui::PointerButton::Primary)
+            && let Some(i) = selected.or(hovered)
+        {
+            let band = &mut self.bands[i];
+            let pos = handle_pos(band) + res.drag_delta();
+
+            band.frequency = grid
+                .frequency_for_x(pos.x)
+                .clamp(*self.frequency_range.start(), *self.frequency_range.end());
+            if band.band_type.has_gain() {
+                band.gain_db = grid
+                    .db_for_y(pos.y)
+                    .clamp(*self.db_range.start(), *self.db_range.end());
+            }
+            res.mark_changed();
+        }
+
+        // Only over a handle, so scrolling elsewhere still scrolls the page
+        if let Some(i) = hovered {
+            let scroll = ui.input_mut(|i| take(&mut i.smooth_scroll_delta)).y;
+            if scroll != 0.0 {
+                let band = &mut self.bands[i];
+                band.q = (band.q * 2.0f32.powf(scroll * 0.005))
+                    .clamp(*self.q_range.start(), *self.q_range.end());
+                res.mark_changed();
+            }
+        }
+
+        // Draw
+        let visuals = *ui.style().interact(&res);
+        let bands: &[EqBand] = self.bands;
+        let sample_rate = self.sample_rate;
+
+        let mut response = FrequencyResponse::default()
+            .frequency_range(self.frequency_range.clone())
+            .db_range(self.db_range.clone());
+        if self.show_band_curves {
+            for (i, band) in bands.iter().enumerate() {
+                if !band.enabled {
+                    continue;
+                }
+                let mut curve = ResponseCurve::from_fn(move |f| band.magnitude(f, sample_rate))
+                    .with_stroke((1.0, visuals.fg_stroke.color.gamma_multiply(0.3)));
+                if *selected == Some(i) {
+                    curve = curve.with_fill(visuals.fg_stroke.color.gamma_multiply(0.1));
+                }
+                response = response.curve(curve);
+            }
+        }
+        response = response.curve(
+            ResponseCurve::from_fn(move |f| eq_magnitude(bands, f, sample_rate))
+                .with_stroke(visuals.fg_stroke),
+        );
+        response.paint(ui, &grid);
+
+        let painter = ui.painter_at(rect);
+        for (i, band) in bands.iter().enumerate() {
+            let center = handle_pos(band);
+            let color = if band.enabled {
+                visuals.fg_stroke.color
+            }
+            else {
+                ui.style().visuals.weak_text_color()
+            };
+
+            if *selected == Some(i) || hovered == Some(i) {
+                painter.circle(
+                    center,
+                    handle_radius * 1.5,
+                    visuals.bg_fill,
+                    (1.0, ui.style().visuals.error_fg_color),
+                );
+            }
+            else {
+                painter.circle(center, handle_radius, visuals.bg_fill, (1.0, color));
+            }
+            painter.text(
+                center,
+                egui::Align2::CENTER_CENTER,
+                format!("{}", i + 1),
+                egui::FontId::proportional(handle_radius * 1.4),
+                color,
+            );
+        }
+
+        res
+    }
+}
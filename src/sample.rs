@@ -0,0 +1,73 @@
+/// PCM sample format that can be normalized to `-1.0..=1.0`
+pub trait Sample: Copy + Send + Sync + 'static {
+    fn to_f32(self) -> f32;
+}
+impl Sample for f32 {
+    fn to_f32(self) -> f32 { self }
+}
+impl Sample for f64 {
+    fn to_f32(self) -> f32 { self as f32 }
+}
+impl Sample for i8 {
+    fn to_f32(self) -> f32 { self as f32 / 128.0 }
+}
+/// Unsigned 8 bit PCM, centered on 128
+impl Sample for u8 {
+    fn to_f32(self) -> f32 { (self as f32 - 128.0) / 128.0 }
+}
+impl Sample for i16 {
+    fn to_f32(self) -> f32 { self as f32 / 32768.0 }
+}
+impl Sample for i32 {
+    fn to_f32(self) -> f32 { (self as f64 / 2147483648.0) as f32 }
+}
+
+/// 24 bit PCM stored in the low bits of an `i32`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(transparent)]
+pub struct I24(pub i32);
+impl I24 {
+    /// Views 24 bit samples stored in `i32`s without copying them
+    pub fn from_slice(samples: &[i32]) -> &[I24] {
+        // SAFETY: I24 is `repr(transparent)` over i32, so both have the same size, alignment and
+        // valid bit patterns. The returned slice borrows `samples` and covers the same length.
+        unsafe { std::slice::from_raw_parts(samples.as_ptr().cast(), samples.len()) }
+    }
+}
+impl Sample for I24 {
+    fn to_f32(self) -> f32 { self.0 as f32 / 8388608.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_scale() {
+        assert_eq!(i8::MIN.to_f32(), -1.0);
+        assert_eq!(i8::MAX.to_f32(), 127.0 / 128.0);
+        assert_eq!(0u8.to_f32(), -1.0);
+        assert_eq!(128u8.to_f32(), 0.0);
+        assert_eq!(255u8.to_f32(), 127.0 / 128.0);
+        assert_eq!(i16::MIN.to_f32(), -1.0);
+        assert_eq!(i16::MAX.to_f32(), 32767.0 / 32768.0);
+        assert_eq!(i32::MIN.to_f32(), -1.0);
+        assert_eq!(i32::MAX.to_f32(), 1.0);
+        assert_eq!(I24(-(1 << 23)).to_f32(), -1.0);
+        assert_eq!(I24((1 << 23) - 1).to_f32(), 8388607.0 / 8388608.0);
+        assert_eq!(I24(0).to_f32(), 0.0);
+        assert_eq!((-0.25f64).to_f32(), -0.25);
+        assert_eq!(0.5f32.to_f32(), 0.5);
+    }
+
+    #[test]
+    fn i24_slice() {
+        let samples = [-(1 << 23), 0, (1 << 22)];
+        assert_eq!(I24::from_slice(&samples), [
+            I24(-(1 << 23)),
+            I24(0),
+            I24(1 << 22)
+        ]);
+        assert_eq!(I24::from_slice(&samples)[2].to_f32(), 0.5);
+    }
+}
@@ -0,0 +1,262 @@
+use std::borrow::Cow;
+use std::ops::RangeInclusive;
+
+use crate::fft::magnitude_spectrum;
+use crate::frequency_grid::{FrequencyGrid, fill_to_baseline};
+use crate::util::to_db_deadzone;
+
+#[derive(Debug, Clone, Copy)]
+pub enum SpectrumInput<'a> {
+    /// Time domain samples, the last `fft_size` samples are analyzed
+    Samples(&'a [f32]),
+    /// Linear magnitudes of the bins `0..=fft_size / 2`
+    Magnitudes(&'a [f32]),
+}
+
+/// Ballistics of a [`Spectrum`] that have to persist between frames.
+#[derive(Debug, Clone, Default)]
+pub struct SpectrumState {
+    pub levels:    Vec<f32>,
+    pub peaks:     Vec<f32>,
+    pub peak_ages: Vec<f32>,
+}
+impl SpectrumState {
+    pub fn reset(&mut self) {
+        self.levels.clear();
+        self.peaks.clear();
+        self.peak_ages.clear();
+    }
+
+    fn update(&mut self, levels: &[f32], dt: f32, smoothing: f32, fall_off: f32, peak_hold: f32) {
+        if self.levels.len() != levels.len() {
+            self.levels = levels.to_vec();
+            self.peaks = levels.to_vec();
+            self.peak_ages = vec![0.0; levels.len()];
+            return;
+        }
+
+        let keep = if smoothing > 0.0 {
+            (-dt / smoothing).exp()
+        }
+        else {
+            0.0
+        };
+
+        for (i, &target) in levels.iter().enumerate() {
+            let level = &mut self.levels[i];
+            if target >= *level {
+                *level += (target - *level) * (1.0 - keep);
+            }
+            else {
+                *level = f32::max(target, *level - fall_off * dt);
+            }
+
+            if *level >= self.peaks[i] {
+                self.peaks[i] = *level;
+                self.peak_ages[i] = 0.0;
+            }
+            else {
+                self.peak_ages[i] += dt;
+                if self.peak_ages[i] > peak_hold {
+                    self.peaks[i] = f32::max(*level, self.peaks[i] - fall_off * dt);
+                }
+            }
+        }
+    }
+}
+
+pub struct Spectrum<'a> {
+    input: SpectrumInput<'a>,
+    sample_rate: f32,
+    state: Option<&'a mut SpectrumState>,
+    fft_size: usize,
+    frequency_range: RangeInclusive<f32>,
+    db_range: RangeInclusive<f32>,
+    smoothing: f32,
+    fall_off: f32,
+    peak_hold: Option<f32>,
+    pixels_per_point: f32,
+    height: f32,
+    stroke: Option<egui::Stroke>,
+    fill: Option<egui::Color32>,
+}
+impl<'a> Spectrum<'a> {
+    pub fn new(input: SpectrumInput<'a>, sample_rate: f32) -> Self {
+        Self {
+            input,
+            sample_rate,
+            state: None,
+            fft_size: 2048,
+            frequency_range: 20.0..=20000.0,
+            db_range: -96.0..=0.0,
+            smoothing: 0.05,
+            fall_off: 24.0,
+            peak_hold: Some(1.0),
+            pixels_per_point: 2.0,
+            height: 200.0,
+            stroke: None,
+            fill: None,
+        }
+    }
+
+    pub fn from_samples(samples: &'a [f32], sample_rate: f32) -> Self {
+        Self::new(SpectrumInput::Samples(samples), sample_rate)
+    }
+
+    pub fn from_magnitudes(magnitudes: &'a [f32], sample_rate: f32) -> Self {
+        Self::new(SpectrumInput::Magnitudes(magnitudes), sample_rate)
+    }
+
+    pub fn state(self, state: &'a mut SpectrumState) -> Self {
+        Self {
+            state: Some(state),
+            ..self
+        }
+    }
+
+    pub fn fft_size(self, fft_size: usize) -> Self {
+        assert!(
+            fft_size.is_power_of_two(),
+            "FFT size must be a power of two"
+        );
+        Self { fft_size, ..self }
+    }
+
+    pub fn frequency_range(self, frequency_range: RangeInclusive<f32>) -> Self {
+        Self {
+            frequency_range,
+            ..self
+        }
+    }
+
+    pub fn db_range(self, db_range: RangeInclusive<f32>) -> Self { Self { db_range, ..self } }
+
+    /// Rise time constant in seconds. Only has an effect if a [`SpectrumState`] is provided.
+    pub fn smoothing(self, smoothing: f32) -> Self { Self { smoothing, ..self } }
+
+    /// Maximum fall speed in dB per second. Only has an effect if a [`SpectrumState`] is provided.
+    pub fn fall_off(self, fall_off: f32) -> Self { Self { fall_off, ..self } }
+
+    /// Seconds to hold peaks before they start falling, `None` hides the peak line.
+    pub fn peak_hold(self, peak_hold: Option<f32>) -> Self { Self { peak_hold, ..self } }
+
+    pub fn pixels_per_point(self, pixels_per_point: f32) -> Self {
+        Self {
+            pixels_per_point,
+            ..self
+        }
+    }
+
+    pub fn height(self, height: f32) -> Self { Self { height, ..self } }
+
+    pub fn stroke(self, stroke: impl Into<egui::Stroke>) -> Self {
+        Self {
+            stroke: Some(stroke.into()),
+            ..self
+        }
+    }
+
+    pub fn fill(self, fill: impl Into<egui::Color32>) -> Self {
+        Self {
+            fill: Some(fill.into()),
+            ..self
+        }
+    }
+
+    fn column_levels(&self, grid: &FrequencyGrid, magnitudes: &[f32]) -> Vec<f32> {
+        let floor_db = *self.db_range.start();
+        if magnitudes.len() < 2 {
+            return Vec::new();
+        }
+
+        let hz_per_bin = self.sample_rate / ((magnitudes.len() - 1) * 2) as f32;
+        let num_columns = (grid.rect.width() / self.pixels_per_point).ceil() as usize + 1;
+        let half_column = self.pixels_per_point * 0.5;
+
+        (0..num_columns)
+            .map(|column| {
+                let x = grid.rect.left() + column as f32 * self.pixels_per_point;
+                let first_bin = grid.frequency_for_x(x - half_column) / hz_per_bin;
+                let last_bin = grid.frequency_for_x(x + half_column) / hz_per_bin;
+
+                let first = (first_bin.ceil() as usize).min(magnitudes.len() - 1);
+                let last = (last_bin.floor() as usize).min(magnitudes.len() - 1);
+
+                let magnitude = if first <= last {
+                    // Several bins per column, show the loudest one
+                    magnitudes[first..=last].iter().copied().fold(0.0, f32::max)
+                }
+                else {
+                    // Less than one bin per column, interpolate
+                    let bin = grid.frequency_for_x(x) / hz_per_bin;
+                    let i = (bin.floor() as usize).min(magnitudes.len() - 2);
+                    egui::lerp(magnitudes[i]..=magnitudes[i + 1], (bin - i as f32).min(1.0))
+                };
+
+                to_db_deadzone(magnitude, floor_db).max(floor_db)
+            })
+            .collect()
+    }
+}
+impl<'a> egui::Widget for Spectrum<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let (rect, response) = ui.allocate_at_least(
+            egui::vec2(ui.available_width(), self.height),
+            egui::Sense::hover(),
+        );
+        let painter = ui.painter_at(rect);
+        let grid = FrequencyGrid::new(rect, self.frequency_range.clone(), self.db_range.clone());
+
+        let magnitudes = match self.input {
+            SpectrumInput::Samples(samples) => {
+                Cow::Owned(magnitude_spectrum(samples, self.fft_size))
+            }
+            SpectrumInput::Magnitudes(magnitudes) => Cow::Borrowed(magnitudes),
+        };
+        let levels = self.column_levels(&grid, &magnitudes);
+
+        let dt = ui.input(|i| i.stable_dt).min(0.1);
+        let has_state = self.state.is_some();
+        let mut fallback_state = SpectrumState::default();
+        let state = self.state.unwrap_or(&mut fallback_state);
+        state.update(
+            &levels,
+            dt,
+            self.smoothing,
+            self.fall_off,
+            self.peak_hold.unwrap_or(f32::INFINITY),
+        );
+        if has_state {
+            ui.ctx().request_repaint();
+        }
+
+        grid.paint(&painter, ui.style());
+
+        let column_x = |i: usize| rect.left() + i as f32 * self.pixels_per_point;
+        let to_points = |values: &[f32]| -> Vec<egui::Pos2> {
+            values
+                .iter()
+                .enumerate()
+                .map(|(i, &db)| egui::pos2(column_x(i), grid.y_for_db(db)))
+                .collect()
+        };
+
+        let stroke = self
+            .stroke
+            .unwrap_or(ui.style().visuals.widgets.noninteractive.fg_stroke);
+        let fill = self.fill.unwrap_or(stroke.color.gamma_multiply(0.25));
+
+        let level_points = to_points(&state.levels);
+        painter.add(fill_to_baseline(&level_points, rect.bottom(), fill));
+        painter.add(egui::Shape::line(level_points, stroke));
+
+        if self.peak_hold.is_some() {
+            painter.add(egui::Shape::line(
+                to_points(&state.peaks),
+                egui::Stroke::new(1.0, stroke.color.gamma_multiply(0.6)),
+            ));
+        }
+
+        response
+    }
+}
@@ -0,0 +1,391 @@
+use std::ops::Range;
+
+/// Tempo change at a position measured in quarter notes from the start
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TempoChange {
+    pub quarter: f64,
+    pub bpm:     f64,
+}
+
+/// Time signature change at the start of a bar (0-based)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeSignatureChange {
+    pub bar: i64,
+    pub numerator: u32,
+    pub denominator: u32,
+}
+impl TimeSignatureChange {
+    pub fn quarters_per_bar(&self) -> f64 { self.numerator as f64 * self.quarters_per_beat() }
+
+    pub fn quarters_per_beat(&self) -> f64 { 4.0 / self.denominator as f64 }
+}
+
+/// Position in bars, beats and ticks. Bars and beats are 1-based like in a DAW.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MusicalTime {
+    pub bar:  i64,
+    pub beat: u32,
+    pub tick: u32,
+}
+impl std::fmt::Display for MusicalTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{:03}", self.bar, self.beat, self.tick)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GridLineKind {
+    /// Start of a bar, with its 1-based bar number
+    Bar(i64),
+    Beat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridLine {
+    pub seconds: f32,
+    pub kind:    GridLineKind,
+}
+
+#[derive(Debug, Clone)]
+pub struct TempoMap {
+    tempo_changes: Vec<TempoChange>,
+    time_signature_changes: Vec<TimeSignatureChange>,
+    pub ticks_per_beat: u32,
+}
+impl Default for TempoMap {
+    fn default() -> Self { Self::new(120.0, 4, 4) }
+}
+impl TempoMap {
+    pub fn new(bpm: f64, numerator: u32, denominator: u32) -> Self {
+        Self {
+            tempo_changes: vec![TempoChange { quarter: 0.0, bpm }],
+            time_signature_changes: vec![TimeSignatureChange {
+                bar: 0,
+                numerator,
+                denominator,
+            }],
+            ticks_per_beat: 960,
+        }
+    }
+
+    pub fn tempo_changes(&self) -> &[TempoChange] { &self.tempo_changes }
+
+    pub fn time_signature_changes(&self) -> &[TimeSignatureChange] { &self.time_signature_changes }
+
+    /// Sets the tempo from `quarter` on, replacing any change at the same position
+    pub fn set_tempo(&mut self, quarter: f64, bpm: f64) {
+        assert!(bpm > 0.0, "Tempo must be positive");
+        let change = TempoChange { quarter, bpm };
+        match self
+            .tempo_changes
+            .binary_search_by(|c| c.quarter.total_cmp(&quarter))
+        {
+            Ok(i) => self.tempo_changes[i] = change,
+            Err(i) => self.tempo_changes.insert(i, change),
+        }
+    }
+
+    /// Sets the time signature from the 0-based `bar` on, replacing any change at the same bar
+    pub fn set_time_signature(&mut self, bar: i64, numerator: u32, denominator: u32) {
+        assert!(
+            numerator > 0 && denominator > 0,
+            "Time signature must be positive"
+        );
+        let change = TimeSignatureChange {
+            bar,
+            numerator,
+            denominator,
+        };
+        match self
+            .time_signature_changes
+            .binary_search_by_key(&bar, |c| c.bar)
+        {
+            Ok(i) => self.time_signature_changes[i] = change,
+            Err(i) => self.time_signature_changes.insert(i, change),
+        }
+    }
+
+    pub fn remove_tempo_change(&mut self, index: usize) {
+        if self.tempo_changes.len() > 1 {
+            self.tempo_changes.remove(index);
+        }
+    }
+
+    pub fn remove_time_signature_change(&mut self, index: usize) {
+        if self.time_signature_changes.len() > 1 {
+            self.time_signature_changes.remove(index);
+        }
+    }
+
+    /// Tempo segments as `(start quarter, start seconds, bpm)`
+    fn tempo_segments(&self) -> impl Iterator<Item = (f64, f64, f64)> + '_ {
+        let mut seconds = 0.0;
+        let mut previous = self.tempo_changes[0];
+        previous.quarter = previous.quarter.min(0.0);
+
+        self.tempo_changes.iter().map(move |change| {
+            seconds += (change.quarter - previous.quarter) * 60.0 / previous.bpm;
+            previous = *change;
+            (change.quarter, seconds, change.bpm)
+        })
+    }
+
+    pub fn seconds_to_quarters(&self, seconds: f32) -> f64 {
+        let seconds = seconds as f64;
+        let (quarter, start, bpm) = self
+            .tempo_segments()
+            .take_while(|(_, start, _)| *start <= seconds)
+            .last()
+            .unwrap_or_else(|| self.tempo_segments().next().unwrap());
+
+        quarter + (seconds - start) * bpm / 60.0
+    }
+
+    pub fn quarters_to_seconds(&self, quarters: f64) -> f32 {
+        let (quarter, start, bpm) = self
+            .tempo_segments()
+            .take_while(|(quarter, ..)| *quarter <= quarters)
+            .last()
+            .unwrap_or_else(|| self.tempo_segments().next().unwrap());
+
+        (start + (quarters - quarter) * 60.0 / bpm) as f32
+    }
+
+    pub fn bpm_at(&self, seconds: f32) -> f64 {
+        let quarters = self.seconds_to_quarters(seconds);
+        self.tempo_changes
+            .iter()
+            .take_while(|c| c.quarter <= quarters)
+            .last()
+            .unwrap_or(&self.tempo_changes[0])
+            .bpm
+    }
+
+    /// Time signature segments as `(change, start quarter)`
+    fn time_signature_segments(&self) -> impl Iterator<Item = (TimeSignatureChange, f64)> + '_ {
+        let mut quarters = 0.0;
+        let mut previous = self.time_signature_changes[0];
+        previous.bar = previous.bar.min(0);
+
+        self.time_signature_changes.iter().map(move |change| {
+            quarters += (change.bar - previous.bar) as f64 * previous.quarters_per_bar();
+            previous = *change;
+            (*change, quarters)
+        })
+    }
+
+    pub fn time_signature_at_bar(&self, bar: i64) -> TimeSignatureChange {
+        *self
+            .time_signature_changes
+            .iter()
+            .take_while(|c| c.bar <= bar)
+            .last()
+            .unwrap_or(&self.time_signature_changes[0])
+    }
+
+    /// Quarter position of the start of the 0-based `bar`
+    pub fn bar_to_quarters(&self, bar: i64) -> f64 {
+        let (change, start) = self
+            .time_signature_segments()
+            .take_while(|(change, _)| change.bar <= bar)
+            .last()
+            .unwrap_or_else(|| self.time_signature_segments().next().unwrap());
+
+        start + (bar - change.bar) as f64 * change.quarters_per_bar()
+    }
+
+    /// 0-based bar containing `quarters`
+    pub fn quarters_to_bar(&self, quarters: f64) -> i64 {
+        let (change, start) = self
+            .time_signature_segments()
+            .take_while(|(_, start)| *start <= quarters)
+            .last()
+            .unwrap_or_else(|| self.time_signature_segments().next().unwrap());
+
+        change.bar + ((quarters - start) / change.quarters_per_bar()).floor() as i64
+    }
+
+    pub fn quarters_to_musical(&self, quarters: f64) -> MusicalTime {
+        let bar = self.quarters_to_bar(quarters);
+        let signature = self.time_signature_at_bar(bar);
+        let beats = (quarters - self.bar_to_quarters(bar)) / signature.quarters_per_beat();
+        let ticks = (beats.fract() * self.ticks_per_beat as f64).floor() as u32;
+
+        MusicalTime {
+            bar:  bar + 1,
+            beat: beats.floor() as u32 + 1,
+            tick: ticks.min(self.ticks_per_beat - 1),
+        }
+    }
+
+    pub fn musical_to_quarters(&self, time: MusicalTime) -> f64 {
+        let bar = time.bar - 1;
+        let signature = self.time_signature_at_bar(bar);
+        let beats = (time.beat.max(1) - 1) as f64 + time.tick as f64 / self.ticks_per_beat as f64;
+
+        self.bar_to_quarters(bar) + beats * signature.quarters_per_beat()
+    }
+
+    pub fn seconds_to_musical(&self, seconds: f32) -> MusicalTime {
+        self.quarters_to_musical(self.seconds_to_quarters(seconds))
+    }
+
+    pub fn musical_to_seconds(&self, time: MusicalTime) -> f32 {
+        self.quarters_to_seconds(self.musical_to_quarters(time))
+    }
+
+    /// Bar and beat lines inside `range`. Beats are left out when they would be closer together
+    /// than `min_spacing` seconds, and bars are thinned out to every 2nd, 4th, ... bar.
+    pub fn grid_lines(&self, range: Range<f32>, min_spacing: f32) -> Vec<GridLine> {
+        let mut lines = Vec::new();
+        let first_bar = self.quarters_to_bar(self.seconds_to_quarters(range.start));
+        let last_bar = self.quarters_to_bar(self.seconds_to_quarters(range.end));
+
+        let bar_duration = |bar: i64| {
+            self.quarters_to_seconds(self.bar_to_quarters(bar + 1))
+                - self.quarters_to_seconds(self.bar_to_quarters(bar))
+        };
+
+        let shortest_bar = (first_bar..=last_bar)
+            .map(bar_duration)
+            .fold(f32::INFINITY, f32::min);
+        let mut bar_step = 1;
+        while shortest_bar * (bar_step as f32) < min_spacing && bar_step < (1 << 20) {
+            bar_step *= 2;
+        }
+
+        for bar in first_bar..=last_bar {
+            let bar_start = self.bar_to_quarters(bar);
+            if bar.rem_euclid(bar_step) == 0 {
+                lines.push(GridLine {
+                    seconds: self.quarters_to_seconds(bar_start),
+                    kind:    GridLineKind::Bar(bar + 1),
+                });
+            }
+
+            let signature = self.time_signature_at_bar(bar);
+            let beat_duration = bar_duration(bar) / signature.numerator as f32;
+            if bar_step == 1 && beat_duration >= min_spacing {
+                for beat in 1..signature.numerator {
+                    lines.push(GridLine {
+                        seconds: self.quarters_to_seconds(
+                            bar_start + beat as f64 * signature.quarters_per_beat(),
+                        ),
+                        kind:    GridLineKind::Beat,
+                    });
+                }
+            }
+        }
+
+        lines.retain(|line| range.contains(&line.seconds));
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) { assert!((a - b).abs() < 1e-4, "{a} != {b}") }
+
+    fn musical(bar: i64, beat: u32, tick: u32) -> MusicalTime { MusicalTime { bar, beat, tick } }
+
+    #[test]
+    fn constant_tempo() {
+        let map = TempoMap::default();
+        assert_close(map.seconds_to_quarters(1.0), 2.0);
+        assert_close(map.seconds_to_quarters(-1.0), -2.0);
+        assert_close(map.quarters_to_seconds(6.0) as f64, 3.0);
+        assert_eq!(map.seconds_to_musical(2.25), musical(2, 1, 480));
+        // Negative times count down from bar 0
+        assert_eq!(map.quarters_to_musical(-1.0), musical(0, 4, 0));
+    }
+
+    #[test]
+    fn tempo_changes() {
+        let mut map = TempoMap::default();
+        map.set_tempo(8.0, 60.0);
+        assert_close(map.quarters_to_seconds(8.0) as f64, 4.0);
+        assert_close(map.quarters_to_seconds(10.0) as f64, 6.0);
+        assert_close(map.seconds_to_quarters(6.0), 10.0);
+        assert_eq!(map.bpm_at(3.0), 120.0);
+        assert_eq!(map.bpm_at(5.0), 60.0);
+
+        // Replacing a change keeps a single entry
+        map.set_tempo(8.0, 240.0);
+        assert_eq!(map.tempo_changes().len(), 2);
+        assert_close(map.quarters_to_seconds(12.0) as f64, 5.0);
+
+        for seconds in [-2.0, 0.0, 1.5, 4.0, 4.5, 30.0] {
+            assert_close(
+                map.quarters_to_seconds(map.seconds_to_quarters(seconds)) as f64,
+                seconds as f64,
+            );
+        }
+    }
+
+    #[test]
+    fn time_signature_changes() {
+        let mut map = TempoMap::default();
+        map.set_time_signature(2, 3, 4);
+        assert_close(map.bar_to_quarters(2), 8.0);
+        assert_close(map.bar_to_quarters(3), 11.0);
+        assert_eq!(map.quarters_to_bar(10.9), 2);
+        assert_eq!(map.quarters_to_bar(11.0), 3);
+        assert_eq!(map.quarters_to_musical(12.5), musical(4, 2, 480));
+        assert_eq!(map.time_signature_at_bar(5).numerator, 3);
+        assert_eq!(map.time_signature_at_bar(1).numerator, 4);
+
+        for quarters in [-3.0, 0.0, 7.75, 8.0, 12.5, 40.25] {
+            assert_close(
+                map.musical_to_quarters(map.quarters_to_musical(quarters)),
+                quarters,
+            );
+        }
+    }
+
+    #[test]
+    fn compound_meter() {
+        // Six eighth notes per bar, so a beat is half a quarter
+        let map = TempoMap::new(120.0, 6, 8);
+        assert_close(map.bar_to_quarters(1), 3.0);
+        assert_eq!(map.quarters_to_musical(4.25), musical(2, 3, 480));
+        assert_close(map.musical_to_quarters(musical(2, 3, 480)), 4.25);
+    }
+
+    #[test]
+    fn grid_lines() {
+        let map = TempoMap::default();
+        let lines = map.grid_lines(0.0..4.01, 0.1);
+        let bars: Vec<(f32, i64)> = lines
+            .iter()
+            .filter_map(|line| match line.kind {
+                GridLineKind::Bar(bar) => Some((line.seconds, bar)),
+                GridLineKind::Beat => None,
+            })
+            .collect();
+        assert_eq!(bars, [(0.0, 1), (2.0, 2), (4.0, 3)]);
+        assert_eq!(
+            lines
+                .iter()
+                .filter(|line| line.kind == GridLineKind::Beat)
+                .count(),
+            6
+        );
+
+        // Beats closer than the spacing are left out
+        assert!(
+            map.grid_lines(0.0..4.01, 1.0)
+                .iter()
+                .all(|line| line.kind != GridLineKind::Beat)
+        );
+
+        // Bars closer than the spacing are thinned out
+        let kinds: Vec<GridLineKind> = map
+            .grid_lines(0.0..4.01, 3.0)
+            .iter()
+            .map(|line| line.kind)
+            .collect();
+        assert_eq!(kinds, [GridLineKind::Bar(1), GridLineKind::Bar(3)]);
+    }
+}
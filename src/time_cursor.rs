@@ -1,5 +1,18 @@
+use std::mem::take;
 use std::ops::{Range, RangeInclusive};
 
+use egui::remap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlayheadFollow {
+    #[default]
+    Off,
+    /// Scrolls continuously so the playhead stays in the middle of the view
+    Continuous,
+    /// Jumps a page ahead once the playhead leaves the view
+    Page,
+}
+
 #[derive(Debug, Clone)]
 pub struct TimeCursor {
     pub time_range: Range<f32>,
@@ -78,6 +91,60 @@ impl TimeCursor {
         self.time_range.end += by;
     }
 
+    /// Scrolling pans and zooms around the pointer, dragging with the middle mouse button pans.
+    /// Shared by all widgets that display time against a cursor so they behave the same.
+    pub fn handle_input(&mut self, ui: &egui::Ui, response: &egui::Response, rect: egui::Rect) {
+        if response.hovered() {
+            let scroll_delta = ui.input_mut(|i| take(&mut i.smooth_scroll_delta));
+
+            let dx = scroll_delta.x / rect.width() * (self.time_range.end - self.time_range.start);
+            self.shift(-dx);
+
+            if response.hovered()
+                && let Some(hover_pos) = ui.input(|i| i.pointer.hover_pos())
+            {
+                let zoom_delta = ui.input(|i| i.zoom_delta());
+
+                let zoom_target = remap(hover_pos.x, rect.x_range(), self.time_range_inclusive());
+                self.zoom_to(
+                    zoom_target,
+                    0.5f32.powf(-scroll_delta.y * 0.01) * zoom_delta,
+                );
+            }
+        }
+
+        if response.dragged_by(egui::PointerButton::Middle) {
+            let dx1 = ui.input(|i| i.pointer.delta().x);
+            let dx = dx1 / rect.width() * (self.time_range.end - self.time_range.start);
+
+            self.shift(-dx);
+        }
+
+        if let Some(hover_pos) = response.hover_pos() {
+            let zoomed = ui.input(|i| i.zoom_delta());
+            let zoom_target = remap(hover_pos.x, rect.x_range(), self.time_range_inclusive());
+            self.zoom_to(zoom_target, zoomed);
+        }
+    }
+
+    pub fn follow(&mut self, position: f32, mode: PlayheadFollow) {
+        let duration = self.time_range.end - self.time_range.start;
+        match mode {
+            PlayheadFollow::Off => {}
+            PlayheadFollow::Continuous => {
+                let center = self.time_range.start + duration / 2.0;
+                if position > center || position < self.time_range.start {
+                    self.shift(position - center);
+                }
+            }
+            PlayheadFollow::Page => {
+                if position >= self.time_range.end || position < self.time_range.start {
+                    self.shift(position - self.time_range.start);
+                }
+            }
+        }
+    }
+
     pub fn move_into_range(&mut self, range: Range<f32>) {
         assert!(range.start < range.end);
 
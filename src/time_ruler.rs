@@ -0,0 +1,331 @@
+use crate::{GridLineKind, TempoMap, TimeCursor};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TimeFormat {
+    /// `1.25s`
+    Seconds,
+    /// `1:05.250`
+    #[default]
+    MinutesSeconds,
+    /// Sample index at the given sample rate
+    Samples { sample_rate: f32 },
+    /// SMPTE timecode `hh:mm:ss:ff` at the given frame rate. Fractional rates are rounded, so
+    /// 29.97 is labelled like 30 (no drop frames).
+    Timecode { fps: f32 },
+}
+impl TimeFormat {
+    /// Formats `seconds` with just enough precision to tell apart labels `step` seconds apart
+    pub fn format(&self, seconds: f32, step: f32) -> String {
+        let sign = if seconds < 0.0 { "-" } else { "" };
+        let abs = seconds.abs();
+        let decimals = (-step.log10().floor()).clamp(0.0, 6.0) as usize;
+
+        match *self {
+            TimeFormat::Seconds => format!("{sign}{abs:.decimals$}s"),
+            TimeFormat::MinutesSeconds => {
+                let decimals = decimals.min(3);
+                // Round before splitting so e.g. 119.9996 becomes `2:00.000` rather than `1:60.000`
+                let scale = 10u64.pow(decimals as u32);
+                let units = (abs as f64 * scale as f64).round() as u64;
+                let minutes = units / (60 * scale);
+                let seconds = (units % (60 * scale)) as f64 / scale as f64;
+                let width = if decimals > 0 { decimals + 3 } else { 2 };
+                format!("{sign}{minutes}:{seconds:0width$.decimals$}")
+            }
+            TimeFormat::Samples { sample_rate } => {
+                format!("{}", (seconds * sample_rate).round() as i64)
+            }
+            TimeFormat::Timecode { fps } => {
+                let fps = fps.round().max(1.0) as i64;
+                let total_frames = (abs * fps as f32).round() as i64;
+                let frames = total_frames % fps;
+                let total_seconds = total_frames / fps;
+                format!(
+                    "{sign}{:02}:{:02}:{:02}:{:02}",
+                    total_seconds / 3600,
+                    total_seconds / 60 % 60,
+                    total_seconds % 60,
+                    frames
+                )
+            }
+        }
+    }
+
+    /// Tick spacings in seconds this format can label cleanly, in ascending order
+    fn steps(&self) -> Vec<f32> {
+        const LONG_STEPS: [f32; 10] = [1.0, 2.0, 5.0, 10.0, 15.0, 30.0, 60.0, 300.0, 900.0, 3600.0];
+
+        match *self {
+            TimeFormat::Seconds | TimeFormat::MinutesSeconds => (-6..0)
+                .flat_map(|e| [1.0, 2.0, 5.0].map(|m| m * 10.0f32.powi(e)))
+                .chain(LONG_STEPS)
+                .collect(),
+            TimeFormat::Samples { sample_rate } => (0..12)
+                .flat_map(|e| [1.0, 2.0, 5.0].map(|m| m * 10.0f32.powi(e) / sample_rate))
+                .collect(),
+            TimeFormat::Timecode { fps } => [1.0, 2.0, 5.0, 10.0]
+                .map(|frames| frames / fps.round().max(1.0))
+                .into_iter()
+                .filter(|step| *step < 1.0)
+                .chain(LONG_STEPS)
+                .collect(),
+        }
+    }
+
+    /// Smallest step where neighbouring ticks are at least `min_spacing` seconds apart
+    pub(crate) fn step_for_spacing(&self, min_spacing: f32) -> f32 {
+        let steps = self.steps();
+        steps
+            .iter()
+            .copied()
+            .find(|step| *step >= min_spacing)
+            .unwrap_or(*steps.last().unwrap())
+    }
+}
+
+const MIN_LABEL_SPACING: f32 = 80.0;
+const MIN_BAR_LABEL_SPACING: f32 = 30.0;
+const MIN_TICK_SPACING: f32 = 8.0;
+
+/// Draws tick marks and labels for the visible time range of `cursor` into `rect`. With a tempo
+/// map, bar numbers and beat ticks are drawn instead of times.
+pub(crate) fn paint_time_ruler(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    cursor: &TimeCursor,
+    format: TimeFormat,
+    tempo_map: Option<&TempoMap>,
+    style: &egui::Style,
+) {
+    let visuals = &style.visuals;
+    let seconds_per_pixel = (cursor.time_range.end - cursor.time_range.start) / rect.width();
+    if !seconds_per_pixel.is_finite() || seconds_per_pixel <= 0.0 {
+        return;
+    }
+
+    if let Some(tempo_map) = tempo_map {
+        paint_musical_ruler(painter, rect, cursor, tempo_map, seconds_per_pixel, style);
+        return;
+    }
+
+    let major_step = format.step_for_spacing(MIN_LABEL_SPACING * seconds_per_pixel);
+    let minor_step = [10.0, 5.0, 2.0]
+        .into_iter()
+        .map(|division| major_step / division)
+        .find(|step| *step / seconds_per_pixel >= MIN_TICK_SPACING);
+
+    let x_for = |time: f32| egui::remap(time, cursor.time_range_inclusive(), rect.x_range());
+    let tick_stroke = egui::Stroke::new(1.0, visuals.weak_text_color());
+
+    if let Some(minor_step) = minor_step {
+        let first = (cursor.time_range.start / minor_step).floor() as i64;
+        let last = (cursor.time_range.end / minor_step).ceil() as i64;
+        for i in first..=last {
+            let x = x_for(i as f32 * minor_step);
+            painter.vline(
+                x,
+                rect.bottom() - rect.height() * 0.25..=rect.bottom(),
+                tick_stroke,
+            );
+        }
+    }
+
+    let first = (cursor.time_range.start / major_step).floor() as i64;
+    let last = (cursor.time_range.end / major_step).ceil() as i64;
+    for i in first..=last {
+        let time = i as f32 * major_step;
+        let x = x_for(time);
+        painter.vline(x, rect.y_range(), tick_stroke);
+        painter.text(
+            egui::pos2(x + 3.0, rect.top()),
+            egui::Align2::LEFT_TOP,
+            format.format(time, major_step),
+            egui::FontId::proportional(10.0),
+            visuals.text_color(),
+        );
+    }
+}
+
+fn paint_musical_ruler(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    cursor: &TimeCursor,
+    tempo_map: &TempoMap,
+    seconds_per_pixel: f32,
+    style: &egui::Style,
+) {
+    let visuals = &style.visuals;
+    let x_for = |time: f32| egui::remap(time, cursor.time_range_inclusive(), rect.x_range());
+    let tick_stroke = egui::Stroke::new(1.0, visuals.weak_text_color());
+
+    for line in tempo_map.grid_lines(
+        cursor.time_range.clone(),
+        MIN_TICK_SPACING * seconds_per_pixel,
+    ) {
+        let x = x_for(line.seconds);
+        let y_range = match line.kind {
+            GridLineKind::Bar(_) => rect.y_range(),
+            GridLineKind::Beat => {
+                egui::Rangef::new(rect.bottom() - rect.height() * 0.25, rect.bottom())
+            }
+        };
+        painter.vline(x, y_range, tick_stroke);
+    }
+
+    for line in tempo_map.grid_lines(
+        cursor.time_range.clone(),
+        MIN_BAR_LABEL_SPACING * seconds_per_pixel,
+    ) {
+        if let GridLineKind::Bar(bar) = line.kind {
+            painter.text(
+                egui::pos2(x_for(line.seconds) + 3.0, rect.top()),
+                egui::Align2::LEFT_TOP,
+                bar.to_string(),
+                egui::FontId::proportional(10.0),
+                visuals.text_color(),
+            );
+        }
+    }
+}
+
+/// Draws bar and beat lines across `rect`
+pub(crate) fn paint_beat_grid(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    cursor: &TimeCursor,
+    tempo_map: &TempoMap,
+    style: &egui::Style,
+) {
+    let seconds_per_pixel = (cursor.time_range.end - cursor.time_range.start) / rect.width();
+    if !seconds_per_pixel.is_finite() || seconds_per_pixel <= 0.0 {
+        return;
+    }
+
+    let color = style.visuals.widgets.noninteractive.bg_stroke.color;
+    for line in tempo_map.grid_lines(
+        cursor.time_range.clone(),
+        MIN_TICK_SPACING * seconds_per_pixel,
+    ) {
+        let x = egui::remap(line.seconds, cursor.time_range_inclusive(), rect.x_range());
+        let stroke = match line.kind {
+            GridLineKind::Bar(_) => egui::Stroke::new(1.0, color),
+            GridLineKind::Beat => egui::Stroke::new(1.0, color.gamma_multiply(0.5)),
+        };
+        painter.vline(x, rect.y_range(), stroke);
+    }
+}
+
+/// A standalone time ruler that scrolls and zooms together with every other widget bound to the
+/// same [`TimeCursor`].
+pub struct TimeRuler<'a> {
+    cursor:    &'a mut TimeCursor,
+    format:    TimeFormat,
+    tempo_map: Option<&'a TempoMap>,
+    height:    f32,
+}
+impl<'a> TimeRuler<'a> {
+    pub fn new(cursor: &'a mut TimeCursor) -> Self {
+        Self {
+            cursor,
+            format: TimeFormat::default(),
+            tempo_map: None,
+            height: 18.0,
+        }
+    }
+
+    pub fn format(self, format: TimeFormat) -> Self { Self { format, ..self } }
+
+    /// Shows bars and beats instead of times
+    pub fn tempo_map(self, tempo_map: &'a TempoMap) -> Self {
+        Self {
+            tempo_map: Some(tempo_map),
+            ..self
+        }
+    }
+
+    pub fn height(self, height: f32) -> Self { Self { height, ..self } }
+
+    /// The inner value is the time the user clicked or dragged to
+    pub fn show(self, ui: &mut egui::Ui) -> egui::InnerResponse<Option<f32>> {
+        let (rect, response) = ui.allocate_at_least(
+            egui::vec2(ui.available_width(), self.height),
+            egui::Sense::click_and_drag(),
+        );
+
+        self.cursor.handle_input(ui, &response, rect);
+
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(
+            rect,
+            0.0,
+            ui.style().visuals.widgets.noninteractive.weak_bg_fill,
+        );
+        paint_time_ruler(
+            &painter,
+            rect,
+            self.cursor,
+            self.format,
+            self.tempo_map,
+            ui.style(),
+        );
+
+        let seek = if response.clicked_by(egui::PointerButton::Primary)
+            || response.dragged_by(egui::PointerButton::Primary)
+        {
+            response
+                .interact_pointer_pos()
+                .map(|p| egui::remap(p.x, rect.x_range(), self.cursor.time_range_inclusive()))
+        }
+        else {
+            None
+        };
+
+        egui::InnerResponse::new(seek, response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seconds() {
+        let format = TimeFormat::Seconds;
+        assert_eq!(format.format(1.25, 0.01), "1.25s");
+        assert_eq!(format.format(12.0, 5.0), "12s");
+        assert_eq!(format.format(-0.5, 0.1), "-0.5s");
+    }
+
+    #[test]
+    fn minutes_seconds() {
+        let format = TimeFormat::MinutesSeconds;
+        assert_eq!(format.format(65.25, 0.001), "1:05.250");
+        assert_eq!(format.format(3.0, 1.0), "0:03");
+        assert_eq!(format.format(-61.5, 0.1), "-1:01.5");
+        // Rounding carries over into the minutes
+        assert_eq!(format.format(119.9996, 0.001), "2:00.000");
+        assert_eq!(format.format(59.96, 0.1), "1:00.0");
+        // At most millisecond precision
+        assert_eq!(format.format(1.0, 0.00001), "0:01.000");
+    }
+
+    #[test]
+    fn samples() {
+        let format = TimeFormat::Samples {
+            sample_rate: 48000.0,
+        };
+        assert_eq!(format.format(0.5, 0.1), "24000");
+        assert_eq!(format.format(-0.01, 0.1), "-480");
+    }
+
+    #[test]
+    fn timecode() {
+        let format = TimeFormat::Timecode { fps: 25.0 };
+        assert_eq!(format.format(3661.52, 0.04), "01:01:01:13");
+        assert_eq!(format.format(-1.2, 0.04), "-00:00:01:05");
+        // Fractional rates are labelled like the nearest whole rate
+        let format = TimeFormat::Timecode { fps: 29.97 };
+        assert_eq!(format.format(1.0, 1.0), "00:00:01:00");
+        assert_eq!(format.format(0.5, 0.1), "00:00:00:15");
+    }
+}
@@ -3,13 +3,40 @@ use std::ops::Range;
 
 use egui::{Modifiers, remap, vec2};
 
+mod waveform_builder;
 mod waveform_data;
+mod waveform_envelope;
+mod waveform_fade;
+mod waveform_job;
 mod waveform_mipmap;
+mod waveform_peak_file;
+mod waveform_snap;
+mod waveform_spectrogram;
+#[cfg(feature = "wav")] mod waveform_wav;
 
+pub use waveform_builder::WaveformShapeBuilder;
 pub use waveform_data::WaveformShape;
+pub use waveform_envelope::{GainPoint, GainPointEdit, WaveformEnvelopeEdit, envelope_gain};
+pub use waveform_fade::FadeCurve;
+use waveform_fade::paint_fade;
+pub use waveform_job::WaveformJob;
 pub use waveform_mipmap::WaveformMipmap;
+pub use waveform_peak_file::{PEAK_FILE_VERSION, PeakFileHeader, PeakFileReader, PeakQuantization};
+use waveform_snap::{DraggedItem, SnapSources};
+pub use waveform_snap::{SnapGrid, SnapKind, SnapTarget, WaveformSnap};
+pub use waveform_spectrogram::{
+    Colormap,
+    FrequencyScale,
+    SpectrogramLevel,
+    SpectrogramStyle,
+    WaveformSpectrogram,
+};
+#[cfg(feature = "wav")]
+pub use waveform_wav::WavChunkReader;
 
-use crate::TimeCursor;
+use crate::time_ruler::{paint_beat_grid, paint_time_ruler};
+use crate::util::{from_db, to_db_deadzone};
+use crate::{PlayheadFollow, TempoMap, TimeCursor, TimeFormat};
 
 // Backwards compatibility
 #[deprecated = "Use WaveformShape instead"]
@@ -22,9 +49,27 @@ pub type Marker = WaveformMarker;
 #[derive(Clone, Copy)]
 pub struct WaveformItem<'a> {
     pub position: f32,
-    pub gain:     f32,
+    pub gain: f32,
     pub waveform: &'a WaveformShape,
-    pub stroke:   Option<egui::Stroke>,
+    pub stroke: Option<egui::Stroke>,
+    pub fill: Option<WaveformFill>,
+    /// Seconds into the waveform where the item starts
+    pub source_offset: f32,
+    /// Length in seconds, `None` plays until the end of the waveform
+    pub length: Option<f32>,
+    /// Index into the lanes of the waveform, ignored when it has none
+    pub lane: usize,
+    /// Fade-in length in seconds
+    pub fade_in: f32,
+    pub fade_in_curve: FadeCurve,
+    /// Fade-out length in seconds
+    pub fade_out: f32,
+    pub fade_out_curve: FadeCurve,
+    /// Clip gain envelope, sorted by time
+    pub envelope: Option<&'a [GainPoint]>,
+    pub spectrogram: Option<&'a WaveformSpectrogram>,
+    /// Fraction of the waveform that is computed, the rest is covered by a placeholder
+    pub loading: Option<f32>,
 }
 impl<'a> WaveformItem<'a> {
     pub fn new(waveform: &'a WaveformShape) -> Self {
@@ -33,10 +78,30 @@ impl<'a> WaveformItem<'a> {
             gain: 1.0,
             waveform,
             stroke: None,
+            fill: None,
+            source_offset: 0.0,
+            length: None,
+            lane: 0,
+            fade_in: 0.0,
+            fade_in_curve: FadeCurve::default(),
+            fade_out: 0.0,
+            fade_out_curve: FadeCurve::default(),
+            envelope: None,
+            spectrogram: None,
+            loading: None,
         }
     }
 
-    pub fn duration(&self) -> f32 { self.waveform.len_seconds() }
+    pub fn duration(&self) -> f32 {
+        let available = (self.waveform.len_seconds() - self.source_offset).max(0.0);
+        self.length
+            .map_or(available, |length| length.min(available))
+    }
+
+    /// Range of the waveform the item shows, in seconds relative to the waveform start
+    pub fn source_range(&self) -> std::ops::Range<f32> {
+        self.source_offset..(self.source_offset + self.duration())
+    }
 
     pub fn time_range(&self) -> std::ops::Range<f32> {
         self.position..(self.duration() + self.position)
@@ -56,7 +121,219 @@ impl<'a> WaveformItem<'a> {
         }
     }
 
+    /// Fills the area between the positive and negative peaks
+    pub fn with_fill(self, fill: WaveformFill) -> Self {
+        Self {
+            fill: Some(fill),
+            ..self
+        }
+    }
+
     pub fn with_gain(self, gain: f32) -> Self { Self { gain, ..self } }
+
+    pub fn with_source_offset(self, source_offset: f32) -> Self {
+        Self {
+            source_offset,
+            ..self
+        }
+    }
+
+    pub fn with_length(self, length: f32) -> Self {
+        Self {
+            length: Some(length),
+            ..self
+        }
+    }
+
+    pub fn with_lane(self, lane: usize) -> Self { Self { lane, ..self } }
+
+    pub fn with_fade_in(self, length: f32, curve: FadeCurve) -> Self {
+        Self {
+            fade_in: length.max(0.0),
+            fade_in_curve: curve,
+            ..self
+        }
+    }
+
+    pub fn with_fade_out(self, length: f32, curve: FadeCurve) -> Self {
+        Self {
+            fade_out: length.max(0.0),
+            fade_out_curve: curve,
+            ..self
+        }
+    }
+
+    /// Pass an empty slice to let the user add the first point
+    pub fn with_envelope(self, envelope: &'a [GainPoint]) -> Self {
+        Self {
+            envelope: Some(envelope),
+            ..self
+        }
+    }
+
+    /// Spectrogram of the same audio as the waveform, shown depending on [`Waveform::display`]
+    pub fn with_spectrogram(self, spectrogram: &'a WaveformSpectrogram) -> Self {
+        Self {
+            spectrogram: Some(spectrogram),
+            ..self
+        }
+    }
+
+    /// Marks the waveform as partially computed, e.g. with [`WaveformJob::progress`]
+    pub fn with_loading(self, progress: f32) -> Self {
+        Self {
+            loading: Some(progress),
+            ..self
+        }
+    }
+
+    /// Gain of the fades at `time` seconds after the item start
+    pub fn fade_gain(&self, time: f32) -> f32 {
+        let mut gain = 1.0;
+        if self.fade_in > 0.0 && time < self.fade_in {
+            gain *= self.fade_in_curve.gain(time / self.fade_in);
+        }
+        let until_end = self.duration() - time;
+        if self.fade_out > 0.0 && until_end < self.fade_out {
+            gain *= self.fade_out_curve.gain(until_end / self.fade_out);
+        }
+        gain
+    }
+}
+
+/// A track in a multi-track [`Waveform`]. Owned by the caller so mute and solo persist.
+#[derive(Debug, Clone)]
+pub struct WaveformLane {
+    pub name:   String,
+    pub height: f32,
+    pub mute:   bool,
+    pub solo:   bool,
+}
+impl Default for WaveformLane {
+    fn default() -> Self {
+        Self {
+            name:   String::new(),
+            height: 80.0,
+            mute:   false,
+            solo:   false,
+        }
+    }
+}
+impl WaveformLane {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_height(self, height: f32) -> Self { Self { height, ..self } }
+
+    /// Whether the lane should be heard, given whether any lane is soloed
+    pub fn is_audible(&self, any_solo: bool) -> bool { !self.mute && (self.solo || !any_solo) }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WaveformChannelLayout {
+    /// Every channel gets its own row inside the item
+    #[default]
+    Stacked,
+    /// All channels are drawn on top of each other
+    Overlaid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WaveformFill {
+    pub color: egui::Color32,
+    /// Color at full scale. When set, the fill blends from `color` at silence to this color.
+    pub peak_color: Option<egui::Color32>,
+}
+impl WaveformFill {
+    pub fn new(color: egui::Color32) -> Self {
+        Self {
+            color,
+            peak_color: None,
+        }
+    }
+
+    pub fn with_gradient(self, peak_color: egui::Color32) -> Self {
+        Self {
+            peak_color: Some(peak_color),
+            ..self
+        }
+    }
+
+    /// Color for a sample of `amplitude`
+    pub fn color_at(&self, amplitude: f32) -> egui::Color32 {
+        match self.peak_color {
+            Some(peak_color) => self
+                .color
+                .lerp_to_gamma(peak_color, amplitude.abs().min(1.0)),
+            None => self.color,
+        }
+    }
+
+    fn gamma_multiply(self, factor: f32) -> Self {
+        Self {
+            color: self.color.gamma_multiply(factor),
+            peak_color: self.peak_color.map(|c| c.gamma_multiply(factor)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AmplitudeScale {
+    #[default]
+    Linear,
+    /// Maps the level in dB from `floor` up to 0 dB onto the channel height
+    Decibels { floor: f32 },
+}
+impl AmplitudeScale {
+    /// Maps `amplitude` to the value drawn, where ±1 are the edges of a channel
+    pub fn apply(&self, amplitude: f32) -> f32 {
+        match *self {
+            AmplitudeScale::Linear => amplitude,
+            AmplitudeScale::Decibels { floor } => {
+                let db = to_db_deadzone(amplitude.abs(), floor).max(floor);
+                amplitude.signum() * remap(db, floor..=0.0, 0.0..=1.0)
+            }
+        }
+    }
+
+    /// Levels in dB for grid lines at least `min_spacing` apart on a channel `height` high
+    pub fn grid_levels(&self, height: f32, min_spacing: f32) -> Vec<f32> {
+        let floor = match *self {
+            AmplitudeScale::Linear => -96.0,
+            AmplitudeScale::Decibels { floor } => floor,
+        };
+        let offset = |db: f32| self.apply(from_db(db)) * height / 2.0;
+
+        let mut levels = Vec::new();
+        let mut last_offset = f32::INFINITY;
+        let mut db = 0.0;
+        while db > floor {
+            let offset = offset(db);
+            if offset < min_spacing {
+                break;
+            }
+            if last_offset - offset >= min_spacing {
+                levels.push(db);
+                last_offset = offset;
+            }
+            db -= 6.0;
+        }
+        levels
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WaveformDisplay {
+    #[default]
+    Outline,
+    /// The spectrogram of items that have one, the outline otherwise
+    Spectrogram,
+    /// The outline on top of the spectrogram
+    SpectrogramAndOutline,
 }
 
 #[derive(Debug, Clone)]
@@ -137,31 +414,328 @@ pub struct WaveformDragged {
     pub start: egui::Vec2,
     pub current: egui::Vec2,
     pub frame_delta: egui::Vec2,
+    /// Time of `start` after snapping
+    pub snapped_start: f32,
+    /// Time of `current` after snapping
+    pub snapped_current: f32,
+}
+
+/// New placement of an item that was moved or trimmed. The caller is responsible for applying it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WaveformItemChanged {
+    /// Index into the items of the waveform
+    pub index: usize,
+    pub position: f32,
+    pub source_offset: f32,
+    pub length: f32,
+    pub lane: usize,
+    pub fade_in: f32,
+    pub fade_out: f32,
+}
+impl WaveformItemChanged {
+    pub fn apply(&self, item: &mut WaveformItem) {
+        item.position = self.position;
+        item.source_offset = self.source_offset;
+        item.length = Some(self.length);
+        item.lane = self.lane;
+        item.fade_in = self.fade_in;
+        item.fade_out = self.fade_out;
+    }
 }
 
 #[derive(Default, Clone, Copy)]
 pub struct WaveformResponse {
     pub clicked: Option<WaveformClicked>,
     pub dragged: Option<WaveformDragged>,
+    /// Index into the editable markers of the marker that was moved or resized this frame
+    pub marker_changed: Option<usize>,
+    /// Time the user clicked or dragged to in the ruler
+    pub seek: Option<f32>,
+    /// What the dragged time snapped to this frame
+    pub snap_target: Option<SnapTarget>,
+    /// The selection was created, adjusted or cleared this frame
+    pub selection_changed: bool,
+    pub item_changed: Option<WaveformItemChanged>,
+    /// Index of the lane whose mute or solo was toggled this frame
+    pub lane_changed: Option<usize>,
+    pub envelope_edit: Option<WaveformEnvelopeEdit>,
+}
+
+/// What a primary button drag started on. Kept in egui's temporary memory while the drag lasts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DragTarget {
+    Seek,
+    Marker(usize, DragPart),
+    /// Selects from `anchor` to the pointer
+    Select {
+        anchor: f32,
+    },
+    Item {
+        index: usize,
+        part:  DragPart,
+        /// Placement of the item when the drag started
+        start: WaveformItemChanged,
+    },
+    ItemFade {
+        index:    usize,
+        fade_out: bool,
+        start:    WaveformItemChanged,
+    },
+    GainPoint {
+        item:  usize,
+        index: usize,
+    },
+}
+
+/// Part of a marker or item being dragged
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DragPart {
+    Start,
+    End,
+    /// Seconds between the start and where it was grabbed
+    Body {
+        grab_offset: f32,
+    },
+}
+
+const GRAB_DISTANCE: f32 = 4.0;
+/// Height of the strip along the top edge by which marker and item bodies are dragged
+const HANDLE_HEIGHT: f32 = 14.0;
+const FADE_HANDLE_SIZE: f32 = 8.0;
+/// Gain at the top edge of an item when drawing its envelope
+const ENVELOPE_MAX_GAIN: f32 = 2.0;
+
+/// Finds the marker edge or body under `pos`. Edges can be grabbed over the full height, the body
+/// only in the label strip at the top so it doesn't get in the way of other interactions.
+fn marker_drag_target(
+    markers: &[WaveformMarker],
+    cursor: &TimeCursor,
+    rect: egui::Rect,
+    pos: egui::Pos2,
+) -> Option<DragTarget> {
+    markers.iter().enumerate().rev().find_map(|(i, m)| {
+        let marker_rect = cursor.time_range_rect(rect, m.time_range());
+        if (pos.x - marker_rect.left()).abs() <= GRAB_DISTANCE {
+            Some(DragTarget::Marker(i, DragPart::Start))
+        }
+        else if m.end.is_some() && (pos.x - marker_rect.right()).abs() <= GRAB_DISTANCE {
+            Some(DragTarget::Marker(i, DragPart::End))
+        }
+        else if m.end.is_some()
+            && marker_rect.x_range().contains(pos.x)
+            && pos.y <= rect.top() + HANDLE_HEIGHT
+        {
+            Some(DragTarget::Marker(i, DragPart::Body {
+                grab_offset: remap(pos.x, rect.x_range(), cursor.time_range_inclusive()) - m.start,
+            }))
+        }
+        else {
+            None
+        }
+    })
+}
+
+/// Finds the item edge or body under `pos`, with the same rules as for markers. `item_rects` holds
+/// the rect of every item inside its lane.
+fn item_drag_target(
+    items: &[WaveformItem],
+    item_rects: &[Option<egui::Rect>],
+    cursor: &TimeCursor,
+    rect: egui::Rect,
+    pos: egui::Pos2,
+) -> Option<DragTarget> {
+    items.iter().enumerate().rev().find_map(|(index, item)| {
+        let item_rect = item_rects[index]?;
+        if !item_rect.y_range().contains(pos.y) {
+            return None;
+        }
+
+        let start = WaveformItemChanged {
+            index,
+            position: item.position,
+            source_offset: item.source_offset,
+            length: item.duration(),
+            lane: item.lane,
+            fade_in: item.fade_in,
+            fade_out: item.fade_out,
+        };
+
+        for (fade_out, handle) in fade_handles(item, item_rect, cursor, rect)
+            .into_iter()
+            .enumerate()
+        {
+            if handle.expand(2.0).contains(pos) {
+                return Some(DragTarget::ItemFade {
+                    index,
+                    fade_out: fade_out == 1,
+                    start,
+                });
+            }
+        }
+
+        let part = if (pos.x - item_rect.left()).abs() <= GRAB_DISTANCE {
+            DragPart::Start
+        }
+        else if (pos.x - item_rect.right()).abs() <= GRAB_DISTANCE {
+            DragPart::End
+        }
+        else if item_rect.x_range().contains(pos.x) && pos.y <= item_rect.top() + HANDLE_HEIGHT {
+            DragPart::Body {
+                grab_offset: remap(pos.x, rect.x_range(), cursor.time_range_inclusive())
+                    - item.position,
+            }
+        }
+        else {
+            return None;
+        };
+
+        Some(DragTarget::Item { index, part, start })
+    })
+}
+
+/// Handles at the top corners of an item that drag the end of the fade-in and the start of the
+/// fade-out
+fn fade_handles(
+    item: &WaveformItem,
+    item_rect: egui::Rect,
+    cursor: &TimeCursor,
+    rect: egui::Rect,
+) -> [egui::Rect; 2] {
+    let x_for = |time: f32| remap(time, cursor.time_range_inclusive(), rect.x_range());
+    let size = egui::Vec2::splat(FADE_HANDLE_SIZE);
+    let y = item_rect.top() + HANDLE_HEIGHT + FADE_HANDLE_SIZE / 2.0;
+    [
+        egui::Rect::from_center_size(
+            egui::pos2(
+                x_for(item.position + item.fade_in) + FADE_HANDLE_SIZE / 2.0,
+                y,
+            ),
+            size,
+        ),
+        egui::Rect::from_center_size(
+            egui::pos2(
+                x_for(item.time_range().end - item.fade_out) - FADE_HANDLE_SIZE / 2.0,
+                y,
+            ),
+            size,
+        ),
+    ]
+}
+
+fn gain_point_pos(
+    item: &WaveformItem,
+    item_rect: egui::Rect,
+    point: GainPoint,
+    cursor: &TimeCursor,
+    rect: egui::Rect,
+) -> egui::Pos2 {
+    egui::pos2(
+        remap(
+            point.time + item.position - item.source_offset,
+            cursor.time_range_inclusive(),
+            rect.x_range(),
+        ),
+        remap(
+            point.gain,
+            0.0..=ENVELOPE_MAX_GAIN,
+            item_rect.bottom()..=item_rect.top(),
+        ),
+    )
+}
+
+/// Finds the envelope point under `pos` as `(item index, point index)`
+fn gain_point_at(
+    items: &[WaveformItem],
+    item_rects: &[Option<egui::Rect>],
+    cursor: &TimeCursor,
+    rect: egui::Rect,
+    pos: egui::Pos2,
+) -> Option<(usize, usize)> {
+    const GRAB_RADIUS: f32 = 6.0;
+
+    items.iter().enumerate().rev().find_map(|(i, item)| {
+        let item_rect = item_rects[i]?;
+        item.envelope?
+            .iter()
+            .position(|p| {
+                gain_point_pos(item, item_rect, *p, cursor, rect).distance(pos) <= GRAB_RADIUS
+            })
+            .map(|index| (i, index))
+    })
+}
+
+/// Time of the selection edge opposite to the one under `pos`, if any
+fn selection_edge_anchor(
+    selection: &Range<f32>,
+    cursor: &TimeCursor,
+    rect: egui::Rect,
+    pos: egui::Pos2,
+) -> Option<f32> {
+    let selection_rect = cursor.time_range_rect(rect, selection.clone());
+    if (pos.x - selection_rect.left()).abs() <= GRAB_DISTANCE {
+        Some(selection.end)
+    }
+    else if (pos.x - selection_rect.right()).abs() <= GRAB_DISTANCE {
+        Some(selection.start)
+    }
+    else {
+        None
+    }
 }
 
 pub struct Waveform<'a> {
     pub data: Vec<WaveformItem<'a>>,
     pub markers: Vec<WaveformMarker>,
+    pub editable_markers: Option<&'a mut [WaveformMarker]>,
     pub cursor: Option<&'a mut TimeCursor>,
     pub pixels_per_point: f32,
     pub zoom_modifier: egui::Modifiers,
     pub height: f32,
+    pub ruler_height: f32,
+    pub time_format: TimeFormat,
+    pub tempo_map: Option<&'a TempoMap>,
+    pub channel_layout: WaveformChannelLayout,
+    pub playhead: Option<f32>,
+    pub follow_playhead: PlayheadFollow,
+    pub snap: WaveformSnap,
+    pub selection: Option<&'a mut Option<Range<f32>>>,
+    pub editable_items: bool,
+    pub editable_envelopes: bool,
+    pub rms: bool,
+    pub amplitude_scale: AmplitudeScale,
+    pub amplitude_grid: bool,
+    pub display: WaveformDisplay,
+    pub spectrogram_style: SpectrogramStyle,
+    pub lanes: Option<&'a mut [WaveformLane]>,
+    pub lane_header_width: f32,
 }
 impl<'a> Default for Waveform<'a> {
     fn default() -> Self {
         Self {
             data: Vec::new(),
             markers: Vec::new(),
+            editable_markers: None,
             cursor: None,
             pixels_per_point: 10.0,
             zoom_modifier: Modifiers::NONE,
             height: 200.0,
+            ruler_height: 0.0,
+            time_format: TimeFormat::default(),
+            tempo_map: None,
+            channel_layout: WaveformChannelLayout::default(),
+            playhead: None,
+            follow_playhead: PlayheadFollow::Off,
+            snap: WaveformSnap::default(),
+            selection: None,
+            editable_items: false,
+            editable_envelopes: false,
+            rms: false,
+            amplitude_scale: AmplitudeScale::default(),
+            amplitude_grid: false,
+            display: WaveformDisplay::default(),
+            spectrogram_style: SpectrogramStyle::default(),
+            lanes: None,
+            lane_header_width: 100.0,
         }
     }
 }
@@ -207,16 +781,150 @@ impl<'a> Waveform<'a> {
         self
     }
 
+    /// Markers that can be moved by dragging their body and resized by dragging their edges.
+    /// Accepts both `&mut [WaveformMarker]` and `&mut Vec<WaveformMarker>`.
+    pub fn editable_markers(self, markers: &'a mut [WaveformMarker]) -> Self {
+        Self {
+            editable_markers: Some(markers),
+            ..self
+        }
+    }
+
     pub fn height(mut self, height: f32) -> Self {
         self.height = height;
         self
     }
 
-    pub fn show(self, ui: &mut egui::Ui) -> egui::InnerResponse<WaveformResponse> {
+    /// Height of the ruler strip along the top edge, clicking or dragging in it seeks. There
+    /// is no ruler by default.
+    pub fn ruler_height(self, ruler_height: f32) -> Self {
+        Self {
+            ruler_height,
+            ..self
+        }
+    }
+
+    pub fn time_format(self, time_format: TimeFormat) -> Self {
+        Self {
+            time_format,
+            ..self
+        }
+    }
+
+    /// Draws a beat grid and bar numbers in the ruler
+    pub fn tempo_map(self, tempo_map: &'a TempoMap) -> Self {
+        Self {
+            tempo_map: Some(tempo_map),
+            ..self
+        }
+    }
+
+    /// Current playback position in seconds
+    pub fn playhead(self, seconds: f32) -> Self {
+        Self {
+            playhead: Some(seconds),
+            ..self
+        }
+    }
+
+    /// Moves the cursor so the playhead stays in view
+    pub fn follow_playhead(self, follow_playhead: PlayheadFollow) -> Self {
+        Self {
+            follow_playhead,
+            ..self
+        }
+    }
+
+    /// What dragged markers, seeks and drags snap to
+    pub fn snap(self, snap: WaveformSnap) -> Self { Self { snap, ..self } }
+
+    /// Enables selecting a time range: dragging creates a selection, shift-dragging extends it
+    /// and dragging an edge adjusts it. Clicking clears it.
+    pub fn selection(self, selection: &'a mut Option<Range<f32>>) -> Self {
+        Self {
+            selection: Some(selection),
+            ..self
+        }
+    }
+
+    /// Lets the user move items by dragging the strip along their top and trim them by dragging
+    /// their edges. The new placement is reported in [`WaveformResponse::item_changed`].
+    pub fn editable_items(self, editable_items: bool) -> Self {
+        Self {
+            editable_items,
+            ..self
+        }
+    }
+
+    /// Lets the user drag the points of item gain envelopes, and add or remove points by double
+    /// clicking. Edits are reported in [`WaveformResponse::envelope_edit`].
+    pub fn editable_envelopes(self, editable_envelopes: bool) -> Self {
+        Self {
+            editable_envelopes,
+            ..self
+        }
+    }
+
+    /// Draws the RMS of each item as a solid body inside a lighter peak outline
+    pub fn rms(self, rms: bool) -> Self { Self { rms, ..self } }
+
+    /// How amplitudes map onto the height of a channel. [`AmplitudeScale::Decibels`] makes quiet
+    /// passages like reverb tails visible.
+    pub fn amplitude_scale(self, amplitude_scale: AmplitudeScale) -> Self {
+        Self {
+            amplitude_scale,
+            ..self
+        }
+    }
+
+    /// Draws labelled horizontal lines at dB levels in every channel
+    pub fn amplitude_grid(self, amplitude_grid: bool) -> Self {
+        Self {
+            amplitude_grid,
+            ..self
+        }
+    }
+
+    pub fn display(self, display: WaveformDisplay) -> Self { Self { display, ..self } }
+
+    pub fn spectrogram_style(self, spectrogram_style: SpectrogramStyle) -> Self {
+        Self {
+            spectrogram_style,
+            ..self
+        }
+    }
+
+    /// Stacks items vertically by their [`WaveformItem::lane`], with a header per lane. Scrolling
+    /// over the headers scrolls the lanes vertically.
+    pub fn lanes(self, lanes: &'a mut [WaveformLane]) -> Self {
+        Self {
+            lanes: Some(lanes),
+            ..self
+        }
+    }
+
+    pub fn lane_header_width(self, lane_header_width: f32) -> Self {
+        Self {
+            lane_header_width,
+            ..self
+        }
+    }
+
+    pub fn channel_layout(self, channel_layout: WaveformChannelLayout) -> Self {
+        Self {
+            channel_layout,
+            ..self
+        }
+    }
+
+    pub fn show(mut self, ui: &mut egui::Ui) -> egui::InnerResponse<WaveformResponse> {
         // Set up parameters
         let entries_range = Iterator::chain(
             self.data.iter().map(WaveformItem::time_range),
-            self.markers.iter().map(WaveformMarker::time_range),
+            self.markers
+                .iter()
+                .chain(self.editable_markers.iter().flat_map(|m| m.iter()))
+                .map(WaveformMarker::time_range),
         )
         .fold(None, |a: Option<std::ops::Range<f32>>, b| {
             Some(match a {
@@ -230,93 +938,817 @@ impl<'a> Waveform<'a> {
         let cursor: &mut TimeCursor = self.cursor.unwrap_or(&mut fallback_cursor);
         cursor.initialize_if_empty(entries_range.clone());
 
-        let (rect, response) = ui.allocate_at_least(
+        let (full_rect, response) = ui.allocate_at_least(
             egui::vec2(ui.available_width(), self.height),
             egui::Sense::click_and_drag(),
         );
-        let painter = ui.painter_at(rect);
+        let painter = ui.painter_at(full_rect);
+        let header_width = if self.lanes.is_some() {
+            self.lane_header_width
+        }
+        else {
+            0.0
+        };
+        // Time is mapped onto `rect`, the lane headers sit to the left of it
+        let rect = full_rect.with_min_x(full_rect.left() + header_width);
+        let ruler_rect = rect.with_max_y(rect.top() + self.ruler_height);
+        let has_ruler = self.ruler_height > 0.0;
+        let content_rect = rect.with_min_y(ruler_rect.bottom());
+        let header_rect = full_rect
+            .with_max_x(rect.left())
+            .with_min_y(content_rect.top());
 
-        if response.hovered() {
-            let scroll_delta = ui.input_mut(|i| take(&mut i.smooth_scroll_delta));
+        // Vertical lane scrolling
+        let lane_scroll_id = response.id.with("lane_scroll");
+        let mut lane_scroll = ui
+            .data(|d| d.get_temp::<f32>(lane_scroll_id))
+            .unwrap_or(0.0);
+        if let Some(lanes) = self.lanes.as_deref() {
+            if ui.rect_contains_pointer(header_rect) {
+                lane_scroll -= ui.input_mut(|i| take(&mut i.smooth_scroll_delta)).y;
+            }
+            let total_height: f32 = lanes.iter().map(|lane| lane.height).sum();
+            lane_scroll = lane_scroll.clamp(0.0, (total_height - content_rect.height()).max(0.0));
+            ui.data_mut(|d| d.insert_temp(lane_scroll_id, lane_scroll));
+        }
+        let lane_rects: Vec<egui::Rect> = match self.lanes.as_deref() {
+            Some(lanes) => lanes
+                .iter()
+                .scan(content_rect.top() - lane_scroll, |top, lane| {
+                    let lane_rect = egui::Rect::from_x_y_ranges(
+                        content_rect.x_range(),
+                        *top..=*top + lane.height,
+                    );
+                    *top += lane.height;
+                    Some(lane_rect)
+                })
+                .collect(),
+            None => vec![content_rect],
+        };
+        let lane_of = |item: &WaveformItem| if self.lanes.is_some() { item.lane } else { 0 };
 
-            let dx =
-                scroll_delta.x / rect.width() * (cursor.time_range.end - cursor.time_range.start);
-            cursor.shift(-dx);
+        if let Some(playhead) = self.playhead
+            && !response.dragged()
+        {
+            cursor.follow(playhead, self.follow_playhead);
+        }
 
-            if response.hovered()
-                && let Some(hover_pos) = ui.input(|i| i.pointer.hover_pos())
-            {
-                let zoom_delta = ui.input(|i| i.zoom_delta());
+        cursor.handle_input(ui, &response, rect);
 
-                let zoom_target = remap(hover_pos.x, rect.x_range(), cursor.time_range_inclusive());
-                cursor.zoom_to(
-                    zoom_target,
-                    0.5f32.powf(-scroll_delta.y * 0.01) * zoom_delta,
-                );
+        // Rect of every item inside its lane, `None` if its lane doesn't exist
+        let item_rects: Vec<Option<egui::Rect>> = self
+            .data
+            .iter()
+            .map(|e| {
+                lane_rects
+                    .get(lane_of(e))
+                    .map(|lane_rect| cursor.time_range_rect(*lane_rect, e.time_range()))
+            })
+            .collect();
+
+        // cursor.move_into_range(0.0..waveform.len_seconds());
+
+        let mut ret = WaveformResponse::default();
+
+        // Drag interactions
+        let drag_target_id = response.id.with("drag_target");
+        let seconds_per_pixel = (cursor.time_range.end - cursor.time_range.start) / rect.width();
+        let editable_markers = self.editable_markers.as_deref_mut().unwrap_or_default();
+
+        // Markers, envelope points and items under a position, in order of priority
+        let edit_target = |editable_markers: &[WaveformMarker], pos: egui::Pos2| {
+            marker_drag_target(editable_markers, cursor, content_rect, pos)
+                .or_else(|| {
+                    self.editable_envelopes
+                        .then(|| gain_point_at(&self.data, &item_rects, cursor, content_rect, pos))
+                        .flatten()
+                        .map(|(item, index)| DragTarget::GainPoint { item, index })
+                })
+                .or_else(|| {
+                    self.editable_items
+                        .then(|| {
+                            item_drag_target(&self.data, &item_rects, cursor, content_rect, pos)
+                        })
+                        .flatten()
+                })
+        };
+
+        if !response.dragged()
+            && let Some(hover_pos) = response.hover_pos()
+        {
+            match edit_target(editable_markers, hover_pos) {
+                Some(
+                    DragTarget::Marker(_, DragPart::Body { .. })
+                    | DragTarget::Item {
+                        part: DragPart::Body { .. },
+                        ..
+                    },
+                )
+                | Some(DragTarget::GainPoint { .. }) => {
+                    ui.ctx().set_cursor_icon(egui::CursorIcon::Grab)
+                }
+                Some(_) => ui.ctx().set_cursor_icon(egui::CursorIcon::ResizeHorizontal),
+                None => {
+                    if let Some(Some(selection)) = self.selection.as_deref()
+                        && selection_edge_anchor(selection, cursor, content_rect, hover_pos)
+                            .is_some()
+                    {
+                        ui.ctx().set_cursor_icon(egui::CursorIcon::ResizeHorizontal);
+                    }
+                }
             }
         }
 
-        if response.dragged_by(egui::PointerButton::Middle) {
-            let dx1 = ui.input(|i| i.pointer.delta().x);
-            let dx = dx1 / rect.width() * (cursor.time_range.end - cursor.time_range.start);
+        let snap_enabled =
+            self.snap.is_enabled() && !self.snap.is_bypassed(ui.input(|i| i.modifiers));
+        let snap = |time: f32,
+                    editable_markers: &[WaveformMarker],
+                    skip_marker: Option<usize>,
+                    dragged_item: Option<DraggedItem>| {
+            if !snap_enabled {
+                return None;
+            }
+            self.snap.snap(time, seconds_per_pixel, &SnapSources {
+                items: &self.data,
+                markers: &self.markers,
+                editable_markers,
+                tempo_map: self.tempo_map,
+                skip_marker,
+                dragged_item,
+            })
+        };
 
-            cursor.shift(-dx);
+        if response.drag_started_by(egui::PointerButton::Primary) {
+            let target = ui.input(|i| i.pointer.press_origin()).and_then(|origin| {
+                if !rect.contains(origin) {
+                    None
+                }
+                else if has_ruler && ruler_rect.contains(origin) {
+                    Some(DragTarget::Seek)
+                }
+                else if let Some(target) = edit_target(editable_markers, origin) {
+                    Some(target)
+                }
+                else {
+                    let selection = self.selection.as_deref()?;
+                    let origin_time =
+                        remap(origin.x, rect.x_range(), cursor.time_range_inclusive());
+                    let shift = ui.input(|i| i.modifiers.shift);
+                    let anchor = selection.as_ref().and_then(|selection| {
+                        let edge = selection_edge_anchor(selection, cursor, content_rect, origin);
+                        // Shift extends away from the edge further from the pointer
+                        let extend = if origin_time - selection.start > selection.end - origin_time
+                        {
+                            selection.start
+                        }
+                        else {
+                            selection.end
+                        };
+                        edge.or(shift.then_some(extend))
+                    });
+                    let anchor = anchor.unwrap_or_else(|| {
+                        snap(origin_time, editable_markers, None, None)
+                            .map_or(origin_time, |t| t.time)
+                    });
+                    Some(DragTarget::Select { anchor })
+                }
+            });
+            ui.data_mut(|d| d.insert_temp(drag_target_id, target));
         }
 
-        if let Some(hover_pos) = response.hover_pos() {
-            let zoomed = ui.input(|i| i.zoom_delta());
-            let zoom_target = remap(hover_pos.x, rect.x_range(), cursor.time_range_inclusive());
-            cursor.zoom_to(zoom_target, zoomed);
+        let drag_target = if response.dragged_by(egui::PointerButton::Primary) {
+            ui.data(|d| d.get_temp::<Option<DragTarget>>(drag_target_id))
+                .flatten()
+        }
+        else {
+            None
+        };
+        if response.drag_stopped() {
+            ui.data_mut(|d| d.remove::<Option<DragTarget>>(drag_target_id));
         }
 
-        // cursor.move_into_range(0.0..waveform.len_seconds());
+        if let Some(target) = drag_target
+            && let Some(pointer) = response.interact_pointer_pos()
+        {
+            let pointer_time = remap(pointer.x, rect.x_range(), cursor.time_range_inclusive());
+
+            match target {
+                DragTarget::Seek => {
+                    ret.snap_target = snap(pointer_time, editable_markers, None, None);
+                    ret.seek = Some(ret.snap_target.map_or(pointer_time, |t| t.time));
+                }
+                DragTarget::Marker(i, part) => {
+                    let time = match part {
+                        DragPart::Start | DragPart::End => pointer_time,
+                        DragPart::Body { grab_offset } => pointer_time - grab_offset,
+                    };
+                    ret.snap_target = snap(time, editable_markers, Some(i), None);
+                    let time = ret.snap_target.map_or(time, |t| t.time);
+
+                    if let Some(m) = editable_markers.get_mut(i) {
+                        match part {
+                            DragPart::Start => m.start = time.min(m.end.unwrap_or(f32::INFINITY)),
+                            DragPart::End => m.end = Some(time.max(m.start)),
+                            DragPart::Body { .. } => {
+                                let delta = time - m.start;
+                                m.start += delta;
+                                m.end = m.end.map(|end| end + delta);
+                            }
+                        }
+                        ret.marker_changed = Some(i);
+                    }
+                    else {
+                        // The marker was removed while dragging it
+                        ui.data_mut(|d| d.remove::<Option<DragTarget>>(drag_target_id));
+                    }
+                }
+                DragTarget::Item { index, part, start } => {
+                    if let Some(item) = self.data.get(index) {
+                        let waveform_length = item.waveform.len_seconds();
+                        let trimmed = Some(DraggedItem {
+                            index,
+                            moving: false,
+                        });
+                        let moved = Some(DraggedItem {
+                            index,
+                            moving: true,
+                        });
+                        let mut changed = start;
+                        match part {
+                            DragPart::Start => {
+                                ret.snap_target =
+                                    snap(pointer_time, editable_markers, None, trimmed);
+                                let time = ret.snap_target.map_or(pointer_time, |t| t.time);
+                                let delta = (time - start.position)
+                                    .clamp((-start.source_offset).min(0.0), start.length.max(0.0));
+                                changed.position += delta;
+                                changed.source_offset += delta;
+                                changed.length -= delta;
+                            }
+                            DragPart::End => {
+                                ret.snap_target =
+                                    snap(pointer_time, editable_markers, None, trimmed);
+                                let time = ret.snap_target.map_or(pointer_time, |t| t.time);
+                                changed.length = (time - start.position)
+                                    .clamp(0.0, (waveform_length - start.source_offset).max(0.0));
+                            }
+                            DragPart::Body { grab_offset } => {
+                                // Snap whichever end of the item is closer to a snap target
+                                let position = pointer_time - grab_offset;
+                                let end = position + start.length;
+                                let snapped_start = snap(position, editable_markers, None, moved);
+                                let snapped_end = snap(end, editable_markers, None, moved);
+                                changed.position = match (snapped_start, snapped_end) {
+                                    (Some(s), Some(e))
+                                        if (e.time - end).abs() < (s.time - position).abs() =>
+                                    {
+                                        ret.snap_target = Some(e);
+                                        e.time - start.length
+                                    }
+                                    (Some(s), _) => {
+                                        ret.snap_target = Some(s);
+                                        s.time
+                                    }
+                                    (None, Some(e)) => {
+                                        ret.snap_target = Some(e);
+                                        e.time - start.length
+                                    }
+                                    (None, None) => position,
+                                };
+                                if let Some(lane) = lane_rects
+                                    .iter()
+                                    .position(|lane_rect| lane_rect.y_range().contains(pointer.y))
+                                    && self.lanes.is_some()
+                                {
+                                    changed.lane = lane;
+                                }
+                            }
+                        }
+                        // Trimming shortens the fades so they still fit into the item
+                        changed.fade_in = changed.fade_in.min(changed.length);
+                        changed.fade_out = changed.fade_out.min(changed.length - changed.fade_in);
+                        ret.item_changed = Some(changed);
+                    }
+                    else {
+                        // The item was removed while dragging it
+                        ui.data_mut(|d| d.remove::<Option<DragTarget>>(drag_target_id));
+                    }
+                }
+                DragTarget::ItemFade {
+                    index,
+                    fade_out,
+                    start,
+                } => {
+                    if index < self.data.len() {
+                        let dragged = DraggedItem {
+                            index,
+                            moving: false,
+                        };
+                        ret.snap_target = snap(pointer_time, editable_markers, None, Some(dragged));
+                        let time = ret.snap_target.map_or(pointer_time, |t| t.time);
+                        let mut changed = start;
+                        if fade_out {
+                            changed.fade_out = (start.position + start.length - time)
+                                .clamp(0.0, (start.length - start.fade_in).max(0.0));
+                        }
+                        else {
+                            changed.fade_in = (time - start.position)
+                                .clamp(0.0, (start.length - start.fade_out).max(0.0));
+                        }
+                        ret.item_changed = Some(changed);
+                    }
+                    else {
+                        // The item was removed while dragging its fade
+                        ui.data_mut(|d| d.remove::<Option<DragTarget>>(drag_target_id));
+                    }
+                }
+                DragTarget::GainPoint { item, index } => {
+                    if let Some(e) = self.data.get(item)
+                        && let Some(points) = e.envelope
+                        && index < points.len()
+                        && let Some(item_rect) = item_rects[item]
+                    {
+                        // Points can't be dragged past their neighbours or out of the item
+                        let min = index
+                            .checked_sub(1)
+                            .map_or(e.source_offset, |i| points[i].time);
+                        let max = points
+                            .get(index + 1)
+                            .map_or(e.source_range().end, |p| p.time);
+                        let time = (pointer_time - e.position + e.source_offset)
+                            .min(max)
+                            .max(min);
+                        let gain = egui::remap_clamp(
+                            pointer.y,
+                            item_rect.bottom()..=item_rect.top(),
+                            0.0..=ENVELOPE_MAX_GAIN,
+                        );
+                        ret.envelope_edit = Some(WaveformEnvelopeEdit {
+                            item,
+                            edit: GainPointEdit::Moved {
+                                index,
+                                point: GainPoint::new(time, gain),
+                            },
+                        });
+                    }
+                    else {
+                        // The item or the point was removed while dragging it
+                        ui.data_mut(|d| d.remove::<Option<DragTarget>>(drag_target_id));
+                    }
+                }
+                DragTarget::Select { anchor } => {
+                    ret.snap_target = snap(pointer_time, editable_markers, None, None);
+                    let time = ret.snap_target.map_or(pointer_time, |t| t.time);
+                    if let Some(selection) = self.selection.as_deref_mut() {
+                        *selection = Some(anchor.min(time)..anchor.max(time));
+                        ret.selection_changed = true;
+                    }
+                }
+            }
+        }
+
+        if self.editable_envelopes
+            && response.double_clicked()
+            && let Some(pos) = response.interact_pointer_pos()
+        {
+            if let Some((item, index)) =
+                gain_point_at(&self.data, &item_rects, cursor, content_rect, pos)
+            {
+                ret.envelope_edit = Some(WaveformEnvelopeEdit {
+                    item,
+                    edit: GainPointEdit::Removed { index },
+                });
+            }
+            else if let Some((item, e, points)) =
+                self.data.iter().enumerate().rev().find_map(|(i, e)| {
+                    let inside = item_rects[i].is_some_and(|r| r.contains(pos));
+                    Some((i, e, e.envelope.filter(|_| inside)?))
+                })
+            {
+                let time = remap(pos.x, rect.x_range(), cursor.time_range_inclusive()) - e.position
+                    + e.source_offset;
+                ret.envelope_edit = Some(WaveformEnvelopeEdit {
+                    item,
+                    edit: GainPointEdit::Added {
+                        index: points.partition_point(|p| p.time <= time),
+                        point: GainPoint::new(time, envelope_gain(points, time)),
+                    },
+                });
+            }
+        }
+
+        let outside_timeline = response
+            .interact_pointer_pos()
+            .is_some_and(|p| !rect.contains(p));
+        let ruler_clicked = has_ruler
+            && response.clicked_by(egui::PointerButton::Primary)
+            && response
+                .interact_pointer_pos()
+                .is_some_and(|p| ruler_rect.contains(p));
+        if ruler_clicked && let Some(pointer) = response.interact_pointer_pos() {
+            ret.seek = Some(remap(
+                pointer.x,
+                rect.x_range(),
+                cursor.time_range_inclusive(),
+            ));
+        }
+
+        if response.clicked_by(egui::PointerButton::Primary)
+            && !ruler_clicked
+            && !outside_timeline
+            && let Some(selection) = self.selection.as_deref_mut()
+            && selection.take().is_some()
+        {
+            ret.selection_changed = true;
+        }
+
+        let markers = || self.markers.iter().chain(editable_markers.iter());
 
         painter.rect(
-            rect,
+            full_rect,
             ui.style().visuals.widgets.noninteractive.corner_radius,
             ui.style().visuals.widgets.noninteractive.bg_fill,
             ui.style().visuals.widgets.noninteractive.bg_stroke,
             egui::StrokeKind::Inside,
         );
 
-        // Draw entry backgrounds
-        for e in self.data.iter() {
+        if has_ruler {
             painter.rect_filled(
-                cursor.time_range_rect(rect, e.time_range()), // Don't use the _clamped variant - we want the rounding to be correct, the renderer will handle clipping
+                ruler_rect,
+                0.0,
+                ui.style().visuals.widgets.noninteractive.weak_bg_fill,
+            );
+            painter.hline(
+                ruler_rect.x_range(),
+                ruler_rect.bottom(),
+                ui.style().visuals.widgets.noninteractive.bg_stroke,
+            );
+            paint_time_ruler(
+                &painter,
+                ruler_rect,
+                cursor,
+                self.time_format,
+                self.tempo_map,
+                ui.style(),
+            );
+        }
+        if let Some(tempo_map) = self.tempo_map {
+            paint_beat_grid(&painter, content_rect, cursor, tempo_map, ui.style());
+        }
+
+        let lanes_painter = painter.with_clip_rect(content_rect);
+        let any_solo = self
+            .lanes
+            .as_deref()
+            .is_some_and(|lanes| lanes.iter().any(|lane| lane.solo));
+        let audible = |e: &WaveformItem| {
+            self.lanes
+                .as_deref()
+                .and_then(|lanes| lanes.get(e.lane))
+                .is_none_or(|lane| lane.is_audible(any_solo))
+        };
+
+        // Draw entry backgrounds
+        for item_rect in item_rects.iter().flatten() {
+            lanes_painter.rect_filled(
+                *item_rect, // Don't use the clamped rect - we want the rounding to be correct, the renderer will handle clipping
                 3.0,
                 ui.style().visuals.extreme_bg_color,
             );
         }
 
+        // Draw item handles
+        if self.editable_items {
+            for item_rect in item_rects.iter().flatten() {
+                lanes_painter.rect_filled(
+                    item_rect.with_max_y(item_rect.top() + HANDLE_HEIGHT),
+                    egui::CornerRadius {
+                        nw: 3,
+                        ne: 3,
+                        sw: 0,
+                        se: 0,
+                    },
+                    ui.style().visuals.widgets.inactive.weak_bg_fill,
+                );
+            }
+        }
+
         // Draw marker backgrounds
-        for m in self.markers.iter() {
+        for m in markers() {
             if cursor.overlaps(m.time_range()) {
-                let rect = cursor.time_range_rect(rect, m.time_range());
+                let rect = cursor.time_range_rect(content_rect, m.time_range());
                 painter.rect_filled(rect, 0.0, m.fill);
             }
         }
 
         // Draw entry waveforms
-        for e in self.data.iter() {
-            if cursor.overlaps(e.time_range()) {
-                let entry_rect = cursor.time_range_rect_clamped(rect, e.time_range());
-                ui.painter_at(entry_rect).add(
-                    e.waveform.get_outline(
+        for (item_index, (e, item_rect)) in self.data.iter().zip(&item_rects).enumerate() {
+            if let Some(item_rect) = item_rect
+                && cursor.overlaps(e.time_range())
+            {
+                let entry_rect = item_rect.intersect(content_rect);
+                let entry_painter = ui.painter_at(entry_rect);
+                let mut stroke = e
+                    .stroke
+                    .unwrap_or(ui.style().visuals.widgets.noninteractive.fg_stroke);
+                let mut fill = e.fill;
+                if !audible(e) {
+                    stroke.color = stroke.color.gamma_multiply(0.4);
+                    fill = fill.map(|fill| fill.gamma_multiply(0.4));
+                }
+                let source_time_range =
+                    cursor.clamp_with_offset(e.source_range(), e.position - e.source_offset);
+
+                let spectrogram = e
+                    .spectrogram
+                    .filter(|_| self.display != WaveformDisplay::Outline);
+                if let Some(spectrogram) = spectrogram {
+                    spectrogram.paint(
+                        ui,
+                        &entry_painter,
+                        response.id.with(("spectrogram", item_index)),
+                        egui::Rect::from_x_y_ranges(entry_rect.x_range(), item_rect.y_range()),
+                        source_time_range.clone(),
+                        &self.spectrogram_style,
+                    );
+                }
+
+                let num_channels =
+                    if spectrogram.is_some() && self.display == WaveformDisplay::Spectrogram {
+                        0
+                    }
+                    else {
+                        e.waveform.num_channels()
+                    };
+                for channel in 0..num_channels {
+                    // Channels are laid out over the unclipped item so scrolling doesn't squash them
+                    let (channel_rect, channel_stroke, channel_fill) = match self.channel_layout {
+                        WaveformChannelLayout::Stacked => {
+                            let channel_height = item_rect.height() / num_channels as f32;
+                            let top = item_rect.top() + channel as f32 * channel_height;
+                            (
+                                egui::Rect::from_x_y_ranges(
+                                    entry_rect.x_range(),
+                                    top..=top + channel_height,
+                                ),
+                                stroke,
+                                fill,
+                            )
+                        }
+                        WaveformChannelLayout::Overlaid => (
+                            item_rect
+                                .with_min_x(entry_rect.left())
+                                .with_max_x(entry_rect.right()),
+                            if channel == 0 {
+                                stroke
+                            }
+                            else {
+                                egui::Stroke::new(stroke.width, stroke.color.gamma_multiply(0.6))
+                            },
+                            if channel == 0 {
+                                fill
+                            }
+                            else {
+                                fill.map(|fill| fill.gamma_multiply(0.6))
+                            },
+                        ),
+                    };
+
+                    if channel > 0 && self.channel_layout == WaveformChannelLayout::Stacked {
+                        entry_painter.hline(
+                            entry_rect.x_range(),
+                            channel_rect.top(),
+                            ui.style().visuals.widgets.noninteractive.bg_stroke,
+                        );
+                    }
+
+                    if self.amplitude_grid {
+                        let grid_stroke = ui.style().visuals.widgets.noninteractive.bg_stroke;
+                        let levels = self
+                            .amplitude_scale
+                            .grid_levels(channel_rect.height(), 12.0);
+                        for db in levels {
+                            let value = self.amplitude_scale.apply(from_db(db));
+                            for value in [value, -value] {
+                                let y = remap(value, 1.0..=-1.0, channel_rect.y_range());
+                                entry_painter.hline(entry_rect.x_range(), y, grid_stroke);
+                            }
+                            entry_painter.text(
+                                egui::pos2(
+                                    entry_rect.left() + 2.0,
+                                    remap(value, 1.0..=-1.0, channel_rect.y_range()),
+                                ),
+                                egui::Align2::LEFT_TOP,
+                                format!("{db:.0}"),
+                                egui::FontId::proportional(9.0),
+                                ui.style().visuals.weak_text_color(),
+                            );
+                        }
+                    }
+
+                    let map = |time: f32, amplitude: f32| {
+                        let gain = e.gain
+                            * e.fade_gain(time - e.source_offset)
+                            * e.envelope.map_or(1.0, |points| envelope_gain(points, time));
+                        self.amplitude_scale.apply(amplitude * gain)
+                    };
+                    if let Some(channel_fill) = channel_fill {
+                        entry_painter.add(e.waveform.get_channel_fill_mapped(
+                            channel,
+                            self.pixels_per_point,
+                            channel_rect,
+                            source_time_range.clone(),
+                            channel_fill,
+                            map,
+                        ));
+                    }
+                    let mut peak_stroke = channel_stroke;
+                    if self.rms {
+                        entry_painter.add(e.waveform.get_channel_rms_mesh_mapped(
+                            channel,
+                            self.pixels_per_point,
+                            channel_rect,
+                            source_time_range.clone(),
+                            channel_stroke.color,
+                            map,
+                        ));
+                        peak_stroke.color = peak_stroke.color.gamma_multiply(0.5);
+                    }
+                    entry_painter.add(e.waveform.get_channel_outline_mapped(
+                        channel,
                         self.pixels_per_point,
-                        entry_rect,
-                        cursor.clamp_with_offset(0.0..e.duration(), e.position),
-                        e.stroke
-                            .unwrap_or(ui.style().visuals.widgets.noninteractive.fg_stroke),
-                        e.gain,
-                    ),
+                        channel_rect,
+                        source_time_range.clone(),
+                        peak_stroke,
+                        map,
+                    ));
+                }
+
+                let x_for = |time: f32| remap(time, cursor.time_range_inclusive(), rect.x_range());
+
+                // Placeholder over the part that is still being computed
+                if let Some(progress) = e.loading {
+                    let loaded_until = e.position - e.source_offset
+                        + progress.clamp(0.0, 1.0) * e.waveform.len_seconds();
+                    let pending_rect =
+                        item_rect.with_min_x(x_for(loaded_until).max(item_rect.left()));
+                    if pending_rect.width() > 0.0 {
+                        entry_painter.rect_filled(
+                            pending_rect,
+                            0.0,
+                            ui.style().visuals.faint_bg_color,
+                        );
+                        entry_painter.text(
+                            pending_rect.intersect(entry_rect).center(),
+                            egui::Align2::CENTER_CENTER,
+                            format!("Loading {:.0}%", progress * 100.0),
+                            egui::FontId::proportional(12.0),
+                            ui.style().visuals.weak_text_color(),
+                        );
+                    }
+                }
+
+                // Fade overlays
+                let fade_fill = ui.style().visuals.extreme_bg_color.gamma_multiply(0.6);
+                let fade_stroke = ui.style().visuals.widgets.noninteractive.fg_stroke;
+                let time_range = e.time_range();
+                if e.fade_in > 0.0 {
+                    paint_fade(
+                        &entry_painter,
+                        e.fade_in_curve,
+                        [x_for(time_range.start), x_for(time_range.start + e.fade_in)],
+                        *item_rect,
+                        fade_fill,
+                        fade_stroke,
+                    );
+                }
+                if e.fade_out > 0.0 {
+                    paint_fade(
+                        &entry_painter,
+                        e.fade_out_curve,
+                        [x_for(time_range.end), x_for(time_range.end - e.fade_out)],
+                        *item_rect,
+                        fade_fill,
+                        fade_stroke,
+                    );
+                }
+
+                // Gain envelope
+                if let Some(points) = e.envelope {
+                    let source_range = e.source_range();
+                    let envelope_stroke = ui.style().visuals.selection.stroke;
+                    let line = std::iter::once(source_range.start)
+                        .chain(
+                            points
+                                .iter()
+                                .map(|p| p.time)
+                                .filter(|time| source_range.contains(time)),
+                        )
+                        .chain([source_range.end])
+                        .map(|time| {
+                            let point = GainPoint::new(time, envelope_gain(points, time));
+                            gain_point_pos(e, *item_rect, point, cursor, rect)
+                        })
+                        .collect();
+                    entry_painter.add(egui::Shape::line(line, envelope_stroke));
+
+                    let hovered = response.hover_pos().and_then(|pos| {
+                        gain_point_at(&self.data, &item_rects, cursor, content_rect, pos)
+                    });
+                    for (index, point) in points.iter().enumerate() {
+                        let radius = if hovered == Some((item_index, index)) {
+                            5.0
+                        }
+                        else {
+                            3.0
+                        };
+                        entry_painter.circle(
+                            gain_point_pos(e, *item_rect, *point, cursor, rect),
+                            radius,
+                            ui.style().visuals.extreme_bg_color,
+                            envelope_stroke,
+                        );
+                    }
+                }
+
+                if self.editable_items {
+                    for handle in fade_handles(e, *item_rect, cursor, rect) {
+                        entry_painter.rect(
+                            handle,
+                            1.0,
+                            ui.style().visuals.widgets.inactive.bg_fill,
+                            ui.style().visuals.widgets.inactive.fg_stroke,
+                            egui::StrokeKind::Inside,
+                        );
+                    }
+                }
+            }
+        }
+
+        // Draw lane headers and separators
+        if let Some(lanes) = self.lanes.as_deref_mut() {
+            let visuals = ui.style().visuals.clone();
+            let separator_painter = painter.with_clip_rect(content_rect.union(header_rect));
+            painter.rect_filled(
+                header_rect,
+                0.0,
+                visuals.widgets.noninteractive.weak_bg_fill,
+            );
+            painter.vline(
+                header_rect.right(),
+                header_rect.y_range(),
+                visuals.widgets.noninteractive.bg_stroke,
+            );
+
+            for (i, (lane, lane_rect)) in lanes.iter_mut().zip(&lane_rects).enumerate() {
+                separator_painter.hline(
+                    full_rect.x_range(),
+                    lane_rect.bottom(),
+                    visuals.widgets.noninteractive.bg_stroke,
+                );
+
+                let lane_header_rect =
+                    egui::Rect::from_x_y_ranges(header_rect.x_range(), lane_rect.y_range());
+                if !lane_header_rect.intersects(header_rect) {
+                    continue;
+                }
+
+                let mut header_ui = ui.new_child(
+                    egui::UiBuilder::new()
+                        .id_salt(("lane_header", i))
+                        .max_rect(lane_header_rect.shrink(4.0)),
                 );
+                header_ui.set_clip_rect(header_rect.intersect(ui.clip_rect()));
+                header_ui.add(egui::Label::new(&lane.name).truncate());
+                header_ui.horizontal(|ui| {
+                    let mute = ui.toggle_value(&mut lane.mute, "M");
+                    let solo = ui.toggle_value(&mut lane.solo, "S");
+                    if mute.changed() || solo.changed() {
+                        ret.lane_changed = Some(i);
+                    }
+                });
             }
         }
 
+        // Draw selection
+        if let Some(Some(selection)) = self.selection.as_deref()
+            && cursor.overlaps(selection.clone())
+        {
+            let selection_rect = cursor.time_range_rect(content_rect, selection.clone());
+            let stroke = ui.style().visuals.selection.stroke;
+            painter.rect_filled(
+                selection_rect,
+                0.0,
+                ui.style().visuals.selection.bg_fill.gamma_multiply(0.3),
+            );
+            painter.vline(selection_rect.left(), content_rect.y_range(), stroke);
+            painter.vline(selection_rect.right(), content_rect.y_range(), stroke);
+        }
+
         // Draw marker start/end lines and text
-        for m in self.markers.iter() {
+        for m in markers() {
             if cursor.overlaps(m.time_range()) {
-                let rect = cursor.time_range_rect(rect, m.time_range());
+                let rect = cursor.time_range_rect(content_rect, m.time_range());
                 painter.line_segment([rect.left_top(), rect.left_bottom()], m.stroke);
                 painter.line_segment([rect.right_top(), rect.right_bottom()], m.stroke);
 
@@ -332,7 +1764,26 @@ impl<'a> Waveform<'a> {
             }
         }
 
-        let mut ret = WaveformResponse::default();
+        // Draw playhead
+        if let Some(playhead) = self.playhead
+            && cursor.time_range_inclusive().contains(&playhead)
+        {
+            let x = remap(playhead, cursor.time_range_inclusive(), rect.x_range());
+            let color = ui.style().visuals.warn_fg_color;
+            painter.vline(x, rect.y_range(), (1.5, color));
+            if has_ruler {
+                painter.add(egui::Shape::convex_polygon(
+                    vec![
+                        egui::pos2(x - 5.0, ruler_rect.top()),
+                        egui::pos2(x + 5.0, ruler_rect.top()),
+                        egui::pos2(x, ruler_rect.bottom()),
+                    ],
+                    color,
+                    egui::Stroke::NONE,
+                ));
+            }
+        }
+
         if let Some(button) = [
             egui::PointerButton::Primary,
             egui::PointerButton::Secondary,
@@ -340,9 +1791,11 @@ impl<'a> Waveform<'a> {
         ]
         .into_iter()
         .find(|b| response.clicked_by(*b))
+            && !ruler_clicked
+            && !outside_timeline
             && let Some(p) = response.interact_pointer_pos()
         {
-            let y = egui::remap(p.y, rect.y_range(), -1.0..=1.0);
+            let y = egui::remap(p.y, content_rect.y_range(), -1.0..=1.0);
             let x = egui::remap(p.x, rect.x_range(), cursor.time_range_inclusive());
             ret.clicked = Some(WaveformClicked {
                 button,
@@ -353,24 +1806,40 @@ impl<'a> Waveform<'a> {
         if let Some(button) = [egui::PointerButton::Primary, egui::PointerButton::Secondary]
             .into_iter()
             .find(|b| response.dragged_by(*b))
+            && drag_target.is_none()
+            && ui
+                .input(|i| i.pointer.press_origin())
+                .is_some_and(|p| rect.contains(p))
             && let Some(current) = response.interact_pointer_pos()
         {
             let start = current - response.total_drag_delta().unwrap_or(egui::Vec2::ZERO);
+            let start_time = remap(start.x, rect.x_range(), cursor.time_range_inclusive());
+            let current_time = remap(current.x, rect.x_range(), cursor.time_range_inclusive());
+            ret.snap_target = snap(current_time, editable_markers, None, None);
 
             ret.dragged = Some(WaveformDragged {
                 button,
                 start: egui::vec2(
                     egui::remap(start.x, rect.x_range(), cursor.time_range_inclusive()),
-                    egui::remap(start.y, rect.y_range(), -1.0..=1.0),
+                    egui::remap(start.y, content_rect.y_range(), -1.0..=1.0),
                 ),
                 current: egui::vec2(
                     egui::remap(current.x, rect.x_range(), cursor.time_range_inclusive()),
-                    egui::remap(current.y, rect.y_range(), -1.0..=1.0),
+                    egui::remap(current.y, content_rect.y_range(), -1.0..=1.0),
                 ),
                 frame_delta: response.drag_delta() / rect.size()
                     * vec2(1.0, cursor.time_range.end - cursor.time_range.start),
+                snapped_start: snap(start_time, editable_markers, None, None)
+                    .map_or(start_time, |t| t.time),
+                snapped_current: ret.snap_target.map_or(current_time, |t| t.time),
             });
         }
+
+        // Highlight what the current drag snapped to
+        if let Some(target) = ret.snap_target {
+            let x = remap(target.time, cursor.time_range_inclusive(), rect.x_range());
+            painter.vline(x, rect.y_range(), ui.style().visuals.selection.stroke);
+        }
         egui::InnerResponse::new(ret, response)
     }
 }
@@ -0,0 +1,217 @@
+use super::waveform_data::wants_coarser_level;
+use super::{WaveformMipmap, WaveformShape};
+use crate::Sample;
+
+/// Builds a [`WaveformShape`] from chunks of samples as they arrive, e.g. while recording.
+/// Every chunk only extends the mipmap levels by the points it completes, so the cost doesn't
+/// grow with the length of the recording.
+pub struct WaveformShapeBuilder {
+    shape: WaveformShape,
+    first_mipmap_scale: usize,
+    mipmap_scale: usize,
+    /// Samples of each channel that don't fill a point of the finest level yet
+    pending: Vec<Vec<f32>>,
+}
+impl WaveformShapeBuilder {
+    pub fn new(
+        num_channels: usize,
+        sample_rate: usize,
+        first_mipmap_scale: usize,
+        mipmap_scale: usize,
+    ) -> Self {
+        assert!(num_channels > 0, "Need at least one channel");
+        assert!(
+            first_mipmap_scale > 0,
+            "First mipmap scale must be positive"
+        );
+        assert!(mipmap_scale > 1, "Mipmap scale must be at least 2");
+
+        let points_per_second = sample_rate as f32 / first_mipmap_scale as f32;
+        Self {
+            shape: WaveformShape {
+                sample_rate,
+                num_samples: 0,
+                channels: vec![
+                    vec![
+                        WaveformMipmap::with_capacity(0, points_per_second)
+                            .with_samples_per_point(first_mipmap_scale)
+                    ];
+                    num_channels
+                ],
+                min_max: (f32::INFINITY, f32::NEG_INFINITY),
+            },
+            first_mipmap_scale,
+            mipmap_scale,
+            pending: vec![Vec::new(); num_channels],
+        }
+    }
+
+    /// The waveform so far. The finest level lags behind by less than `first_mipmap_scale`
+    /// samples, coarser levels by less than one of their points.
+    pub fn shape(&self) -> &WaveformShape { &self.shape }
+
+    /// Appends samples to a single channel waveform
+    pub fn push<S: Sample>(&mut self, samples: &[S]) { self.push_planar(&[samples]) }
+
+    /// Appends one sample slice per channel
+    pub fn push_planar<S: Sample>(&mut self, channels: &[&[S]]) {
+        assert_eq!(
+            channels.len(),
+            self.shape.num_channels(),
+            "Need samples for every channel"
+        );
+        assert!(
+            channels.iter().all(|c| c.len() == channels[0].len()),
+            "All channels should have the same length"
+        );
+
+        self.shape.num_samples += channels[0].len();
+        for (channel, samples) in channels.iter().enumerate() {
+            self.pending[channel].extend(samples.iter().map(|x| x.to_f32()));
+            self.extend_channel(channel, false);
+        }
+    }
+
+    /// Appends interleaved samples (`L R L R ...` for stereo)
+    pub fn push_interleaved<S: Sample>(&mut self, samples: &[S]) {
+        let num_channels = self.shape.num_channels();
+        let channels: Vec<Vec<S>> = (0..num_channels)
+            .map(|channel| {
+                samples
+                    .iter()
+                    .skip(channel)
+                    .step_by(num_channels)
+                    .take(samples.len() / num_channels)
+                    .copied()
+                    .collect()
+            })
+            .collect();
+        let channels: Vec<&[S]> = channels.iter().map(Vec::as_slice).collect();
+        self.push_planar(&channels);
+    }
+
+    /// Adds the points for the remaining samples, giving the same shape as
+    /// [`WaveformShape::generate_planar`] with all samples at once
+    pub fn finish(mut self) -> WaveformShape {
+        for channel in 0..self.shape.num_channels() {
+            self.extend_channel(channel, true);
+        }
+        self.shape
+    }
+
+    /// Turns the pending samples of `channel` into points and merges them into the coarser
+    /// levels. With `flush`, incomplete points are added as well.
+    fn extend_channel(&mut self, channel: usize, flush: bool) {
+        let pending = &mut self.pending[channel];
+        let mipmaps = &mut self.shape.channels[channel];
+        let first_index = self.shape.num_samples - pending.len();
+        let complete = if flush {
+            pending.len()
+        }
+        else {
+            pending.len() - pending.len() % self.first_mipmap_scale
+        };
+        let num_points = mipmaps[0].len();
+        for start in (0..complete).step_by(self.first_mipmap_scale) {
+            let end = (start + self.first_mipmap_scale).min(complete);
+            mipmaps[0].push_samples(
+                &pending[start..end],
+                first_index + start,
+                self.shape.sample_rate,
+            );
+        }
+        pending.drain(..complete);
+
+        let finest = &mipmaps[0];
+        for (positive, negative) in finest.positive_peaks[num_points..]
+            .iter()
+            .zip(&finest.negative_peaks[num_points..])
+        {
+            self.shape.min_max.0 = self.shape.min_max.0.min(negative.y);
+            self.shape.min_max.1 = self.shape.min_max.1.max(positive.y);
+        }
+
+        let mut level = 0;
+        loop {
+            if level + 1 == mipmaps.len() {
+                if !wants_coarser_level(mipmaps[level].len(), self.mipmap_scale) {
+                    break;
+                }
+                let finer = &mipmaps[level];
+                mipmaps.push(
+                    WaveformMipmap::with_capacity(
+                        0,
+                        finer.points_per_second / self.mipmap_scale as f32,
+                    )
+                    .with_samples_per_point(finer.samples_per_point * self.mipmap_scale),
+                );
+            }
+
+            let (finer, coarser) = mipmaps.split_at_mut(level + 1);
+            let (finer, coarser) = (&finer[level], &mut coarser[0]);
+            let num_groups = if flush {
+                finer.len().div_ceil(self.mipmap_scale)
+            }
+            else {
+                finer.len() / self.mipmap_scale
+            };
+            for group in coarser.len()..num_groups {
+                let start = group * self.mipmap_scale;
+                coarser.push_merged(finer, start..(start + self.mipmap_scale).min(finer.len()));
+            }
+            level += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_generate_planar() {
+        // Neither the finest nor the coarser levels divide the length evenly
+        let num_samples = 100_003;
+        let channels: Vec<Vec<f32>> = (0..2)
+            .map(|channel| {
+                (0..num_samples)
+                    .map(|i| ((i * (channel + 3)) as f32 * 0.001).sin() * (i % 977) as f32 / 977.0)
+                    .collect()
+            })
+            .collect();
+        let planar: Vec<&[f32]> = channels.iter().map(Vec::as_slice).collect();
+        let expected = WaveformShape::generate_planar(&planar, 48000, 7, 3);
+
+        let mut builder = WaveformShapeBuilder::new(2, 48000, 7, 3);
+        let mut start = 0;
+        for chunk_size in [1, 6, 999, 13, 4096, 2].into_iter().cycle() {
+            let end = (start + chunk_size).min(num_samples);
+            let chunk: Vec<&[f32]> = channels.iter().map(|c| &c[start..end]).collect();
+            builder.push_planar(&chunk);
+            start = end;
+            if start == num_samples {
+                break;
+            }
+        }
+        let shape = builder.finish();
+
+        assert_eq!(shape.num_samples, expected.num_samples);
+        assert_eq!(shape.min_max, expected.min_max);
+        for channel in 0..2 {
+            let (levels, expected_levels) = (
+                shape.channel_mipmaps(channel),
+                expected.channel_mipmaps(channel),
+            );
+            assert!(expected_levels.len() > 2);
+            assert_eq!(levels.len(), expected_levels.len());
+            for (level, expected_level) in levels.iter().zip(expected_levels) {
+                assert_eq!(level.points_per_second, expected_level.points_per_second);
+                assert_eq!(level.samples_per_point, expected_level.samples_per_point);
+                assert_eq!(level.num_samples, expected_level.num_samples);
+                assert_eq!(level.positive_peaks, expected_level.positive_peaks);
+                assert_eq!(level.negative_peaks, expected_level.negative_peaks);
+                assert_eq!(level.rms, expected_level.rms);
+            }
+        }
+    }
+}
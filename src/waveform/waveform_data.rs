@@ -1,20 +1,89 @@
 use std::num::NonZeroUsize;
 use std::sync::{Arc, RwLock};
 
-#[derive(Default)]
+use crate::Sample;
+use crate::frequency_grid::strip_mesh;
+
+#[derive(Default, Clone)]
 pub struct WaveformShape {
     pub sample_rate: usize,
+    /// Number of samples per channel
     pub num_samples: usize,
-    pub mipmaps:     Vec<super::WaveformMipmap>,
+    /// Mipmap pyramid of each channel
+    pub channels:    Vec<Vec<super::WaveformMipmap>>,
     pub min_max:     (f32, f32),
 }
 impl WaveformShape {
-    pub fn generate(
-        samples: &[f32],
+    /// Generates a single channel waveform. Integer PCM like `i16`, `u8` or [`crate::I24`] is
+    /// normalized on the fly.
+    pub fn generate<S: Sample>(
+        samples: &[S],
+        sample_rate: usize,
+        first_mipmap_scale: usize,
+        mipmap_scale: usize,
+    ) -> Self {
+        Self::generate_planar(&[samples], sample_rate, first_mipmap_scale, mipmap_scale)
+    }
+
+    /// Generates a waveform from one sample slice per channel
+    pub fn generate_planar<S: Sample>(
+        channels: &[&[S]],
         sample_rate: usize,
         first_mipmap_scale: usize,
         mipmap_scale: usize,
     ) -> Self {
+        assert!(!channels.is_empty(), "Need at least one channel");
+        assert!(
+            channels.iter().all(|c| c.len() == channels[0].len()),
+            "All channels should have the same length"
+        );
+
+        let channel_mipmaps = channels
+            .iter()
+            .map(|samples| {
+                Self::generate_mipmaps(samples, sample_rate, first_mipmap_scale, mipmap_scale)
+            })
+            .collect();
+
+        Self::from_channels(channel_mipmaps, sample_rate, channels[0].len())
+    }
+
+    /// Generates a waveform from interleaved samples (`L R L R ...` for stereo)
+    pub fn generate_interleaved<S: Sample>(
+        samples: &[S],
+        num_channels: usize,
+        sample_rate: usize,
+        first_mipmap_scale: usize,
+        mipmap_scale: usize,
+    ) -> Self {
+        assert!(num_channels > 0, "Need at least one channel");
+
+        let num_samples = samples.len() / num_channels;
+        let mut channel_samples: Vec<S> = Vec::with_capacity(num_samples);
+
+        let channel_mipmaps = (0..num_channels)
+            .map(|channel| {
+                channel_samples.clear();
+                channel_samples.extend(samples.iter().skip(channel).step_by(num_channels));
+                channel_samples.truncate(num_samples);
+                Self::generate_mipmaps(
+                    &channel_samples,
+                    sample_rate,
+                    first_mipmap_scale,
+                    mipmap_scale,
+                )
+            })
+            .collect();
+
+        Self::from_channels(channel_mipmaps, sample_rate, num_samples)
+    }
+
+    fn generate_mipmaps<S: Sample>(
+        samples: &[S],
+        sample_rate: usize,
+        first_mipmap_scale: usize,
+        mipmap_scale: usize,
+    ) -> Vec<super::WaveformMipmap> {
         let mut mipmaps = Vec::new();
         let first_mipmap_scale = NonZeroUsize::new(first_mipmap_scale).unwrap();
         let mipmap_scale = NonZeroUsize::new(mipmap_scale).unwrap();
@@ -26,52 +95,105 @@ impl WaveformShape {
         ));
         loop {
             let last_mipmap = mipmaps.last().unwrap();
-            if last_mipmap.len() / mipmap_scale.get() < 256
-                || last_mipmap.len() / 2 <= mipmap_scale.get()
-            {
+            if !wants_coarser_level(last_mipmap.len(), mipmap_scale.get()) {
                 break;
             }
 
             mipmaps.push(last_mipmap.shrink(mipmap_scale));
         }
 
-        let min_max = (
-            mipmaps[mipmaps.len() - 1]
-                .negative_peaks
-                .iter()
-                .map(|p| p.y)
-                .fold(f32::INFINITY, f32::min),
-            mipmaps[mipmaps.len() - 1]
-                .positive_peaks
-                .iter()
-                .map(|p| p.y)
-                .fold(f32::NEG_INFINITY, f32::max),
-        );
+        mipmaps
+    }
+
+    fn from_channels(
+        channels: Vec<Vec<super::WaveformMipmap>>,
+        sample_rate: usize,
+        num_samples: usize,
+    ) -> Self {
+        let min_max = channels
+            .iter()
+            .map(|mipmaps| {
+                let coarsest = mipmaps.last().unwrap();
+                (
+                    coarsest
+                        .negative_peaks
+                        .iter()
+                        .map(|p| p.y)
+                        .fold(f32::INFINITY, f32::min),
+                    coarsest
+                        .positive_peaks
+                        .iter()
+                        .map(|p| p.y)
+                        .fold(f32::NEG_INFINITY, f32::max),
+                )
+            })
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |a, b| {
+                (a.0.min(b.0), a.1.max(b.1))
+            });
 
         Self {
             sample_rate,
-            num_samples: samples.len(),
-            mipmaps,
+            num_samples,
+            channels,
             min_max,
         }
     }
 
+    pub fn num_channels(&self) -> usize { self.channels.len() }
+
+    pub fn channel_mipmaps(&self, channel: usize) -> &[super::WaveformMipmap] {
+        &self.channels[channel]
+    }
+
+    // Backwards compatibility
+    /// Mipmaps of the first channel, which used to be the only one
+    #[deprecated = "Use channel_mipmaps or the channels field instead"]
+    pub fn mipmaps(&self) -> &[super::WaveformMipmap] { self.channel_mipmaps(0) }
+
     pub fn get_points(
         &self,
         desired_points: usize,
         time_range: std::ops::Range<f32>,
     ) -> [&[egui::Vec2]; 2] {
+        self.get_channel_points(0, desired_points, time_range)
+    }
+
+    pub fn get_channel_points(
+        &self,
+        channel: usize,
+        desired_points: usize,
+        time_range: std::ops::Range<f32>,
+    ) -> [&[egui::Vec2]; 2] {
+        self.mipmap_for(channel, desired_points, time_range.clone())
+            .point_range(time_range)
+    }
+
+    /// RMS points of `channel`, each at the center of the samples it covers
+    pub fn get_channel_rms_points(
+        &self,
+        channel: usize,
+        desired_points: usize,
+        time_range: std::ops::Range<f32>,
+    ) -> Vec<egui::Vec2> {
+        self.mipmap_for(channel, desired_points, time_range.clone())
+            .rms_range(time_range)
+    }
+
+    fn mipmap_for(
+        &self,
+        channel: usize,
+        desired_points: usize,
+        time_range: std::ops::Range<f32>,
+    ) -> &super::WaveformMipmap {
+        let mipmaps = &self.channels[channel];
         let target_points_per_second = desired_points as f32 / (time_range.end - time_range.start);
 
         // Take the first mipmap that has at least target_points_per_second
-        let mipmap = self
-            .mipmaps
+        mipmaps
             .iter()
             .take_while(|mip| mip.points_per_second > target_points_per_second)
             .last()
-            .unwrap_or(&self.mipmaps[0]);
-
-        mipmap.point_range(time_range)
+            .unwrap_or(&mipmaps[0])
     }
 
     pub fn get_outline(
@@ -81,10 +203,44 @@ impl WaveformShape {
         time_range: std::ops::Range<f32>,
         stroke: impl Into<egui::Stroke>,
         scale_y: f32,
+    ) -> egui::epaint::PathShape {
+        self.get_channel_outline(0, pixels_per_point, rect, time_range, stroke, scale_y)
+    }
+
+    pub fn get_channel_outline(
+        &self,
+        channel: usize,
+        pixels_per_point: f32,
+        rect: egui::Rect,
+        time_range: std::ops::Range<f32>,
+        stroke: impl Into<egui::Stroke>,
+        scale_y: f32,
+    ) -> egui::epaint::PathShape {
+        self.get_channel_outline_mapped(
+            channel,
+            pixels_per_point,
+            rect,
+            time_range,
+            stroke,
+            |_, y| y * scale_y,
+        )
+    }
+
+    /// Like [`Self::get_channel_outline`], but `map` turns the time and amplitude of every point
+    /// into the value drawn, where ±1 are the edges of `rect`
+    pub fn get_channel_outline_mapped(
+        &self,
+        channel: usize,
+        pixels_per_point: f32,
+        rect: egui::Rect,
+        time_range: std::ops::Range<f32>,
+        stroke: impl Into<egui::Stroke>,
+        map: impl Fn(f32, f32) -> f32,
     ) -> egui::epaint::PathShape {
         let desired_num_points = (rect.width() / pixels_per_point).ceil() as usize;
 
-        let [max_points, min_points] = self.get_points(desired_num_points, time_range.clone());
+        let [max_points, min_points] =
+            self.get_channel_points(channel, desired_num_points, time_range.clone());
 
         egui::epaint::PathShape::closed_line(
             max_points
@@ -94,7 +250,7 @@ impl WaveformShape {
                 .map(|p| {
                     egui::pos2(
                         egui::remap(p.x, time_range.start..=time_range.end, rect.x_range()),
-                        egui::remap(p.y * scale_y, 1.0..=-1.0, rect.y_range()),
+                        egui::remap(map(p.x, p.y), 1.0..=-1.0, rect.y_range()),
                     )
                 })
                 .collect(),
@@ -102,8 +258,122 @@ impl WaveformShape {
         )
     }
 
+    pub fn get_fill(
+        &self,
+        pixels_per_point: f32,
+        rect: egui::Rect,
+        time_range: std::ops::Range<f32>,
+        fill: super::WaveformFill,
+        scale_y: f32,
+    ) -> egui::Mesh {
+        self.get_channel_fill(0, pixels_per_point, rect, time_range, fill, scale_y)
+    }
+
+    pub fn get_channel_fill(
+        &self,
+        channel: usize,
+        pixels_per_point: f32,
+        rect: egui::Rect,
+        time_range: std::ops::Range<f32>,
+        fill: super::WaveformFill,
+        scale_y: f32,
+    ) -> egui::Mesh {
+        self.get_channel_fill_mapped(channel, pixels_per_point, rect, time_range, fill, |_, y| {
+            y * scale_y
+        })
+    }
+
+    /// Triangle strip between the positive and negative peaks. Unlike filling the path from
+    /// [`Self::get_channel_outline_mapped`], this works for any shape of waveform.
+    pub fn get_channel_fill_mapped(
+        &self,
+        channel: usize,
+        pixels_per_point: f32,
+        rect: egui::Rect,
+        time_range: std::ops::Range<f32>,
+        fill: super::WaveformFill,
+        map: impl Fn(f32, f32) -> f32,
+    ) -> egui::Mesh {
+        let desired_num_points = (rect.width() / pixels_per_point).ceil() as usize;
+        let [max_points, min_points] =
+            self.get_channel_points(channel, desired_num_points, time_range.clone());
+
+        // Both peak lines are sampled at the union of their point times, with a middle vertex at
+        // zero so a gradient runs outwards from silence
+        let mut times: Vec<f32> = max_points.iter().chain(min_points).map(|p| p.x).collect();
+        times.sort_by(f32::total_cmp);
+        times.dedup();
+
+        strip_mesh(times.iter().map(|&time| {
+            let x = egui::remap(time, time_range.start..=time_range.end, rect.x_range());
+            let max = map(time, interpolate_points(max_points, time));
+            let min = map(time, interpolate_points(min_points, time));
+            let middle = 0.0f32.clamp(min.min(max), max.max(min));
+            [max, middle, min].map(|value| {
+                (
+                    egui::pos2(x, egui::remap(value, 1.0..=-1.0, rect.y_range())),
+                    fill.color_at(value),
+                )
+            })
+        }))
+    }
+
+    /// Filled body between the positive and negative RMS of `channel`, mapped like
+    /// [`Self::get_channel_outline_mapped`]
+    pub fn get_channel_rms_mesh_mapped(
+        &self,
+        channel: usize,
+        pixels_per_point: f32,
+        rect: egui::Rect,
+        time_range: std::ops::Range<f32>,
+        color: egui::Color32,
+        map: impl Fn(f32, f32) -> f32,
+    ) -> egui::Mesh {
+        let desired_num_points = (rect.width() / pixels_per_point).ceil() as usize;
+        let points = self.get_channel_rms_points(channel, desired_num_points, time_range.clone());
+
+        strip_mesh(points.iter().map(|p| {
+            let x = egui::remap(p.x, time_range.start..=time_range.end, rect.x_range());
+            [map(p.x, p.y), map(p.x, -p.y)].map(|y| {
+                (
+                    egui::pos2(x, egui::remap(y, 1.0..=-1.0, rect.y_range())),
+                    color,
+                )
+            })
+        }))
+    }
+
+    /// Finds the zero crossing closest to `time` within `max_distance` seconds, using the finest
+    /// mipmap level
+    pub fn nearest_zero_crossing(
+        &self,
+        channel: usize,
+        time: f32,
+        max_distance: f32,
+    ) -> Option<f32> {
+        let mipmap = self.channels.get(channel)?.first()?;
+        if mipmap.is_empty() {
+            return None;
+        }
+
+        let [positive, negative] = mipmap.point_range((time - max_distance)..(time + max_distance));
+
+        // The peaks of each bucket in the order they occur approximate the original samples
+        let mut points: Vec<egui::Vec2> = positive.iter().chain(negative).copied().collect();
+        points.sort_by(|a, b| a.x.total_cmp(&b.x));
+
+        points
+            .windows(2)
+            .filter(|w| (w[0].y <= 0.0) != (w[1].y <= 0.0))
+            .map(|w| w[0].x + (w[1].x - w[0].x) * (w[0].y / (w[0].y - w[1].y)))
+            .filter(|x| (x - time).abs() <= max_distance)
+            .min_by(|a, b| (a - time).abs().total_cmp(&(b - time).abs()))
+    }
+
     pub fn len_seconds(&self) -> f32 { self.num_samples as f32 / self.sample_rate as f32 }
 
+    /// Generates the waveform on a separate thread. See [`super::WaveformJob`] for progress,
+    /// cancellation and partial results.
     pub fn calculate_into_async(
         output: Arc<RwLock<Option<Self>>>,
         samples: Vec<f32>,
@@ -121,3 +391,19 @@ impl WaveformShape {
         });
     }
 }
+
+/// Whether a level of `len` points is long enough to get a coarser level
+pub(super) fn wants_coarser_level(len: usize, mipmap_scale: usize) -> bool {
+    len / mipmap_scale >= 256 && len / 2 > mipmap_scale
+}
+
+/// Linearly interpolates the `y` of `points`, sorted by `x`, at `x`
+fn interpolate_points(points: &[egui::Vec2], x: f32) -> f32 {
+    let i = points.partition_point(|p| p.x < x);
+    match (i.checked_sub(1).map(|i| points[i]), points.get(i)) {
+        (Some(a), Some(b)) if b.x > a.x => egui::lerp(a.y..=b.y, (x - a.x) / (b.x - a.x)),
+        (_, Some(b)) => b.y,
+        (Some(a), None) => a.y,
+        (None, None) => 0.0,
+    }
+}
@@ -0,0 +1,59 @@
+/// Point of a clip gain envelope
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GainPoint {
+    /// Seconds relative to the start of the waveform, so the envelope stays with the audio when
+    /// the item is trimmed
+    pub time: f32,
+    /// Linear gain
+    pub gain: f32,
+}
+impl GainPoint {
+    pub fn new(time: f32, gain: f32) -> Self { Self { time, gain } }
+}
+
+/// Linear interpolation between the points, which have to be sorted by time. Holds the first and
+/// last gain outside of the points and is unity without points.
+pub fn envelope_gain(points: &[GainPoint], time: f32) -> f32 {
+    let next = points.partition_point(|p| p.time <= time);
+    match (
+        next.checked_sub(1).map(|i| points[i]),
+        points.get(next).copied(),
+    ) {
+        (None, None) => 1.0,
+        (Some(p), None) | (None, Some(p)) => p.gain,
+        (Some(a), Some(b)) => egui::remap(time, a.time..=b.time, a.gain..=b.gain),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GainPointEdit {
+    Moved { index: usize, point: GainPoint },
+    Added { index: usize, point: GainPoint },
+    Removed { index: usize },
+}
+
+/// Edit of the gain envelope of an item. The caller is responsible for applying it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WaveformEnvelopeEdit {
+    /// Index into the items of the waveform
+    pub item: usize,
+    pub edit: GainPointEdit,
+}
+impl WaveformEnvelopeEdit {
+    /// Edits of points that no longer exist are ignored
+    pub fn apply(&self, points: &mut Vec<GainPoint>) {
+        match self.edit {
+            GainPointEdit::Moved { index, point } => {
+                if let Some(p) = points.get_mut(index) {
+                    *p = point;
+                }
+            }
+            GainPointEdit::Added { index, point } => points.insert(index.min(points.len()), point),
+            GainPointEdit::Removed { index } => {
+                if index < points.len() {
+                    points.remove(index);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::f32::consts::FRAC_PI_2;
+
+use crate::frequency_grid::fill_to_baseline;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FadeCurve {
+    #[default]
+    Linear,
+    /// Keeps the summed power constant when crossfading with the opposite fade
+    EqualPower,
+    /// Linear in dB over a 60 dB range
+    Exponential,
+    /// Smoothstep, slow at both ends
+    SCurve,
+}
+impl FadeCurve {
+    /// Gain of a fade-in at `t` in `0..=1`. A fade-out uses the same curve mirrored in time.
+    pub fn gain(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            FadeCurve::Linear => t,
+            FadeCurve::EqualPower => (t * FRAC_PI_2).sin(),
+            FadeCurve::Exponential => (1000.0f32.powf(t) - 1.0) / 999.0,
+            FadeCurve::SCurve => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// Draws the attenuated area of a fade over `rect` and the curve itself. `x_range` goes from the
+/// silent end to the full volume end of the fade, so fade-outs pass a reversed range.
+pub(crate) fn paint_fade(
+    painter: &egui::Painter,
+    curve: FadeCurve,
+    x_range: [f32; 2],
+    rect: egui::Rect,
+    fill: egui::Color32,
+    stroke: egui::Stroke,
+) {
+    const NUM_POINTS: usize = 32;
+
+    let points: Vec<egui::Pos2> = (0..=NUM_POINTS)
+        .map(|i| {
+            let t = i as f32 / NUM_POINTS as f32;
+            egui::pos2(
+                egui::lerp(x_range[0]..=x_range[1], t),
+                egui::lerp(rect.bottom()..=rect.top(), curve.gain(t)),
+            )
+        })
+        .collect();
+
+    painter.add(fill_to_baseline(&points, rect.top(), fill));
+    painter.add(egui::Shape::line(points, stroke));
+}
@@ -0,0 +1,153 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+
+use super::{WaveformShape, WaveformShapeBuilder};
+
+/// Samples per channel processed between checks for cancellation
+const CHUNK_SIZE: usize = 1 << 16;
+
+struct JobState {
+    progress:  AtomicU32,
+    cancelled: AtomicBool,
+    finished:  AtomicBool,
+    shape:     RwLock<Arc<WaveformShape>>,
+}
+
+/// Handle to a [`WaveformShape`] being generated on a background thread. The job is cancelled
+/// when the handle is dropped.
+pub struct WaveformJob {
+    state: Arc<JobState>,
+}
+impl WaveformJob {
+    /// Generates a single channel waveform
+    pub fn spawn(
+        samples: Vec<f32>,
+        sample_rate: usize,
+        first_mipmap_scale: usize,
+        mipmap_scale: usize,
+        ctx: Option<egui::Context>,
+    ) -> Self {
+        Self::spawn_planar(
+            vec![samples],
+            sample_rate,
+            first_mipmap_scale,
+            mipmap_scale,
+            ctx,
+        )
+    }
+
+    /// Generates a waveform from one sample buffer per channel. `ctx` is repainted whenever a
+    /// new partial shape is available.
+    pub fn spawn_planar(
+        channels: Vec<Vec<f32>>,
+        sample_rate: usize,
+        first_mipmap_scale: usize,
+        mipmap_scale: usize,
+        ctx: Option<egui::Context>,
+    ) -> Self {
+        assert!(!channels.is_empty(), "Need at least one channel");
+        assert!(
+            channels.iter().all(|c| c.len() == channels[0].len()),
+            "All channels should have the same length"
+        );
+
+        let num_samples = channels[0].len();
+        let mut builder = WaveformShapeBuilder::new(
+            channels.len(),
+            sample_rate,
+            first_mipmap_scale,
+            mipmap_scale,
+        );
+        // Partial shapes span the whole length so items don't grow while loading
+        let mut initial = builder.shape().clone();
+        initial.num_samples = num_samples;
+
+        let state = Arc::new(JobState {
+            progress:  AtomicU32::new(0.0f32.to_bits()),
+            cancelled: AtomicBool::new(false),
+            finished:  AtomicBool::new(false),
+            shape:     RwLock::new(Arc::new(initial)),
+        });
+
+        let job_state = state.clone();
+        std::thread::spawn(move || {
+            let state = job_state;
+            let mut published = 0.0;
+            for start in (0..num_samples).step_by(CHUNK_SIZE) {
+                if state.cancelled.load(Ordering::Relaxed) {
+                    return;
+                }
+
+                let end = (start + CHUNK_SIZE).min(num_samples);
+                let chunk: Vec<&[f32]> = channels.iter().map(|c| &c[start..end]).collect();
+                builder.push_planar(&chunk);
+
+                // Publish every few percent rather than after every chunk
+                let progress = end as f32 / num_samples as f32;
+                if progress - published >= 0.05 && end < num_samples {
+                    published = progress;
+                    let mut shape = state.shape.write().unwrap_or_else(PoisonError::into_inner);
+                    // Only copies the shape if a reader still holds on to the previous one
+                    append_new_points(Arc::make_mut(&mut shape), builder.shape());
+                    drop(shape);
+                    state.progress.store(progress.to_bits(), Ordering::Relaxed);
+                    if let Some(ctx) = &ctx {
+                        ctx.request_repaint();
+                    }
+                }
+            }
+
+            *state.shape.write().unwrap_or_else(PoisonError::into_inner) =
+                Arc::new(builder.finish());
+            state.progress.store(1.0f32.to_bits(), Ordering::Relaxed);
+            state.finished.store(true, Ordering::Release);
+            if let Some(ctx) = ctx {
+                ctx.request_repaint();
+            }
+        });
+
+        Self { state }
+    }
+
+    /// Fraction of the waveform covered by [`Self::shape`], from 0 to 1
+    pub fn progress(&self) -> f32 { f32::from_bits(self.state.progress.load(Ordering::Relaxed)) }
+
+    pub fn is_finished(&self) -> bool { self.state.finished.load(Ordering::Acquire) }
+
+    /// Stops the job at the next chunk. The shape keeps whatever was computed so far.
+    pub fn cancel(&self) { self.state.cancelled.store(true, Ordering::Relaxed) }
+
+    pub fn is_cancelled(&self) -> bool { self.state.cancelled.load(Ordering::Relaxed) }
+
+    /// The finished shape, or a partial one with points up to about [`Self::progress`]
+    pub fn shape(&self) -> Arc<WaveformShape> {
+        self.state
+            .shape
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+impl Drop for WaveformJob {
+    fn drop(&mut self) { self.cancel() }
+}
+
+/// Appends the points `partial` has gained since `published` was last updated. Points the
+/// builder has pushed never change, so the existing ones don't need to be copied again.
+fn append_new_points(published: &mut WaveformShape, partial: &WaveformShape) {
+    published.min_max = partial.min_max;
+    for (levels, partial_levels) in published.channels.iter_mut().zip(&partial.channels) {
+        for (level, partial_level) in levels.iter_mut().zip(partial_levels) {
+            let len = level.len();
+            level
+                .positive_peaks
+                .extend_from_slice(&partial_level.positive_peaks[len..]);
+            level
+                .negative_peaks
+                .extend_from_slice(&partial_level.negative_peaks[len..]);
+            level.rms.extend_from_slice(&partial_level.rms[len..]);
+            level.num_samples = partial_level.num_samples;
+        }
+        levels.extend_from_slice(&partial_levels[levels.len()..]);
+    }
+}
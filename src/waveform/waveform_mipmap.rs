@@ -1,10 +1,18 @@
 use std::num::NonZeroUsize;
 
+use crate::Sample;
+
 #[derive(Clone, Debug)]
 pub struct WaveformMipmap {
     pub points_per_second: f32,
-    pub positive_peaks:    Vec<egui::Vec2>,
-    pub negative_peaks:    Vec<egui::Vec2>,
+    /// Number of samples each point covers, except for the last one which may cover fewer
+    pub samples_per_point: usize,
+    /// Number of samples covered by all points
+    pub num_samples: usize,
+    pub positive_peaks: Vec<egui::Vec2>,
+    pub negative_peaks: Vec<egui::Vec2>,
+    /// Root mean square of each point
+    pub rms: Vec<f32>,
 }
 
 impl WaveformMipmap {
@@ -13,11 +21,22 @@ impl WaveformMipmap {
         simplify_path(&mut self.negative_peaks, max_cost / self.points_per_second);
     }
 
+    /// Points are taken to cover a single sample until [`Self::samples_per_point`] is set
     pub fn with_capacity(len: usize, points_per_second: f32) -> Self {
         Self {
             points_per_second,
+            samples_per_point: 1,
+            num_samples: 0,
             negative_peaks: Vec::with_capacity(len),
             positive_peaks: Vec::with_capacity(len),
+            rms: Vec::with_capacity(len),
+        }
+    }
+
+    pub(crate) fn with_samples_per_point(self, samples_per_point: usize) -> Self {
+        Self {
+            samples_per_point,
+            ..self
         }
     }
 
@@ -31,7 +50,12 @@ impl WaveformMipmap {
         self.positive_peaks.is_empty()
     }
 
-    pub fn from_samples(data: &[f32], sample_rate: usize, shrink_factor: NonZeroUsize) -> Self {
+    /// Integer samples are normalized as they are read, without converting `data` up front
+    pub fn from_samples<S: Sample>(
+        data: &[S],
+        sample_rate: usize,
+        shrink_factor: NonZeroUsize,
+    ) -> Self {
         let shrink_factor = shrink_factor.get();
 
         assert!(
@@ -42,65 +66,123 @@ impl WaveformMipmap {
         let mut result = Self::with_capacity(
             data.len().div_ceil(shrink_factor),
             sample_rate as f32 / shrink_factor as f32,
-        );
+        )
+        .with_samples_per_point(shrink_factor);
 
         for i in (0..data.len()).step_by(shrink_factor) {
-            let subrange = &data[i..(i + shrink_factor).min(data.len())];
-
-            let (positive_peak_idx, positive_peak_value) = Self::positive_peak_in_samples(subrange);
-            let (negative_peak_idx, negative_peak_value) = Self::negative_peak_in_samples(subrange);
-
-            result.positive_peaks.push(egui::vec2(
-                (i + positive_peak_idx) as f32 / sample_rate as f32,
-                positive_peak_value,
-            ));
-            result.negative_peaks.push(egui::vec2(
-                (i + negative_peak_idx) as f32 / sample_rate as f32,
-                negative_peak_value,
-            ));
+            result.push_samples(
+                &data[i..(i + shrink_factor).min(data.len())],
+                i,
+                sample_rate,
+            );
         }
 
         result
     }
 
+    /// Appends a point summarizing `samples`, where `first_index` is the index of the first one
+    pub(crate) fn push_samples<S: Sample>(
+        &mut self,
+        samples: &[S],
+        first_index: usize,
+        sample_rate: usize,
+    ) {
+        let (positive_peak_idx, positive_peak_value) = Self::positive_peak_in_samples(samples);
+        let (negative_peak_idx, negative_peak_value) = Self::negative_peak_in_samples(samples);
+
+        self.positive_peaks.push(egui::vec2(
+            (first_index + positive_peak_idx) as f32 / sample_rate as f32,
+            positive_peak_value,
+        ));
+        self.negative_peaks.push(egui::vec2(
+            (first_index + negative_peak_idx) as f32 / sample_rate as f32,
+            negative_peak_value,
+        ));
+
+        let sum_of_squares: f64 = samples
+            .iter()
+            .map(|x| {
+                let x = x.to_f32() as f64;
+                x * x
+            })
+            .sum();
+        self.rms
+            .push((sum_of_squares / samples.len().max(1) as f64).sqrt() as f32);
+        self.num_samples += samples.len();
+    }
+
     pub fn shrink(&self, factor: NonZeroUsize) -> Self {
         let factor = factor.get();
 
         let mut result =
-            Self::with_capacity(self.len() / factor, self.points_per_second / factor as f32);
+            Self::with_capacity(self.len() / factor, self.points_per_second / factor as f32)
+                .with_samples_per_point(self.samples_per_point * factor);
 
         for i in (0..self.positive_peaks.len()).step_by(factor) {
-            let subrange = i..(i + factor).min(self.positive_peaks.len());
-
-            result.positive_peaks.push(Self::positive_peak_in_points(
-                &self.positive_peaks[subrange.clone()],
-            ));
-            result.negative_peaks.push(Self::negative_peak_in_points(
-                &self.negative_peaks[subrange.clone()],
-            ));
+            result.push_merged(self, i..(i + factor).min(self.positive_peaks.len()));
         }
 
         result
     }
 
-    pub(crate) fn positive_peak_in_samples(data: &[f32]) -> (usize, f32) {
-        data.iter().enumerate().fold(
-            (0, f32::NEG_INFINITY),
-            |acc, (i, &x)| {
-                if x > acc.1 { (i, x) } else { acc }
-            },
-        )
+    /// Appends a point merging the points in `subrange` of the finer level `finer`
+    pub(crate) fn push_merged(&mut self, finer: &Self, subrange: std::ops::Range<usize>) {
+        self.positive_peaks.push(Self::positive_peak_in_points(
+            &finer.positive_peaks[subrange.clone()],
+        ));
+        self.negative_peaks.push(Self::negative_peak_in_points(
+            &finer.negative_peaks[subrange.clone()],
+        ));
+
+        // Weighting by the number of samples makes the merged RMS exact
+        let (sum_of_squares, sample_count) = subrange.fold((0.0, 0), |(sum, count), i| {
+            let n = finer.sample_count(i);
+            let rms = finer.rms[i] as f64;
+            (sum + rms * rms * n as f64, count + n)
+        });
+        self.rms
+            .push((sum_of_squares / sample_count.max(1) as f64).sqrt() as f32);
+        self.num_samples += sample_count;
     }
 
-    pub(crate) fn negative_peak_in_samples(data: &[f32]) -> (usize, f32) {
-        data.iter().enumerate().fold(
-            (0, f32::INFINITY),
-            |acc, (i, &x)| {
-                if x < acc.1 { (i, x) } else { acc }
-            },
+    /// Number of samples covered by point `index`
+    pub fn sample_count(&self, index: usize) -> usize {
+        self.samples_per_point.min(
+            self.num_samples
+                .saturating_sub(index * self.samples_per_point),
         )
     }
 
+    /// Time of the center of the samples covered by point `index`
+    fn center_time(&self, index: usize) -> f32 {
+        let fraction = self.sample_count(index) as f32 / self.samples_per_point as f32;
+        (index as f32 + fraction / 2.0) / self.points_per_second
+    }
+
+    pub(crate) fn positive_peak_in_samples<S: Sample>(data: &[S]) -> (usize, f32) {
+        data.iter()
+            .map(|x| x.to_f32())
+            .enumerate()
+            .fold(
+                (0, f32::NEG_INFINITY),
+                |acc, (i, x)| {
+                    if x > acc.1 { (i, x) } else { acc }
+                },
+            )
+    }
+
+    pub(crate) fn negative_peak_in_samples<S: Sample>(data: &[S]) -> (usize, f32) {
+        data.iter()
+            .map(|x| x.to_f32())
+            .enumerate()
+            .fold(
+                (0, f32::INFINITY),
+                |acc, (i, x)| {
+                    if x < acc.1 { (i, x) } else { acc }
+                },
+            )
+    }
+
     pub(crate) fn positive_peak_in_points(data: &[egui::Vec2]) -> egui::Vec2 {
         data.iter()
             .fold(egui::vec2(0.0, f32::NEG_INFINITY), |acc, &x| {
@@ -120,10 +202,28 @@ impl WaveformMipmap {
             point_range_helper(&self.negative_peaks, time_range.clone()),
         ]
     }
+
+    /// RMS points at the center of the samples they cover, from the last one before
+    /// `time_range` to the first one after it
+    pub(crate) fn rms_range(&self, time_range: std::ops::Range<f32>) -> Vec<egui::Vec2> {
+        let index_at = |time: f32| (time * self.points_per_second).max(0.0) as usize;
+        let start = index_at(time_range.start)
+            .saturating_sub(1)
+            .min(self.rms.len());
+        let end = (index_at(time_range.end) + 2)
+            .min(self.rms.len())
+            .max(start);
+        (start..end)
+            .map(|i| egui::vec2(self.center_time(i), self.rms[i]))
+            .collect()
+    }
 }
 
 fn point_range_helper(points: &[egui::Vec2], range: std::ops::Range<f32>) -> &[egui::Vec2] {
     // TODO: Optimize this using binary search or point density
+    if points.is_empty() {
+        return points;
+    }
     let start = match points.binary_search_by(|p| p.x.partial_cmp(&range.start).unwrap()) {
         Err(a) => a,
         Ok(a) => a,
@@ -0,0 +1,461 @@
+//! Binary peak file format for caching a [`WaveformShape`] on disk. All numbers are little
+//! endian. The file starts with a fixed size header, followed by a table with the offset of every
+//! mipmap level, followed by the level data. Every point is stored as the quantized positive
+//! peak, negative peak and RMS, plus the positions of the peaks inside the point. Values are
+//! clamped to ±1, so peaks of audio that clips are stored as full scale.
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use super::{WaveformMipmap, WaveformShape};
+
+const MAGIC: [u8; 4] = *b"EAPK";
+pub const PEAK_FILE_VERSION: u16 = 1;
+const HEADER_SIZE: u64 = 40;
+
+/// Resolution of the stored values, which are clamped to ±1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PeakQuantization {
+    /// 5 bytes per point
+    I8,
+    /// 10 bytes per point
+    #[default]
+    I16,
+}
+impl PeakQuantization {
+    fn bytes(&self) -> usize {
+        match self {
+            PeakQuantization::I8 => 1,
+            PeakQuantization::I16 => 2,
+        }
+    }
+
+    fn max(&self) -> f32 {
+        match self {
+            PeakQuantization::I8 => i8::MAX as f32,
+            PeakQuantization::I16 => i16::MAX as f32,
+        }
+    }
+
+    fn write_value(&self, out: &mut Vec<u8>, value: f32) {
+        let value = (value.clamp(-1.0, 1.0) * self.max()).round();
+        match self {
+            PeakQuantization::I8 => out.push(value as i8 as u8),
+            PeakQuantization::I16 => out.extend((value as i16).to_le_bytes()),
+        }
+    }
+
+    fn read_value(&self, bytes: &[u8]) -> f32 {
+        let value = match self {
+            PeakQuantization::I8 => bytes[0] as i8 as f32,
+            PeakQuantization::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f32,
+        };
+        value / self.max()
+    }
+
+    /// Writes a fraction in `0..=1` using the full unsigned range
+    fn write_fraction(&self, out: &mut Vec<u8>, fraction: f32) {
+        let fraction = fraction.clamp(0.0, 1.0);
+        match self {
+            PeakQuantization::I8 => out.push((fraction * u8::MAX as f32).round() as u8),
+            PeakQuantization::I16 => {
+                out.extend(((fraction * u16::MAX as f32).round() as u16).to_le_bytes())
+            }
+        }
+    }
+
+    fn read_fraction(&self, bytes: &[u8]) -> f32 {
+        match self {
+            PeakQuantization::I8 => bytes[0] as f32 / u8::MAX as f32,
+            PeakQuantization::I16 => {
+                u16::from_le_bytes([bytes[0], bytes[1]]) as f32 / u16::MAX as f32
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LevelEntry {
+    offset:     u64,
+    num_points: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeakFileHeader {
+    pub version: u16,
+    pub quantization: PeakQuantization,
+    pub sample_rate: usize,
+    /// Number of samples per channel
+    pub num_samples: usize,
+    pub num_channels: usize,
+    pub first_mipmap_scale: usize,
+    pub mipmap_scale: usize,
+    pub min_max: (f32, f32),
+    levels: Vec<Vec<LevelEntry>>,
+}
+impl PeakFileHeader {
+    pub fn num_levels(&self, channel: usize) -> usize { self.levels[channel].len() }
+
+    /// Number of samples each point of `level` covers. Can only overflow for levels that aren't
+    /// in the file.
+    pub fn samples_per_point(&self, level: usize) -> usize {
+        self.first_mipmap_scale * self.mipmap_scale.pow(level as u32)
+    }
+}
+
+impl WaveformShape {
+    /// Writes the waveform as a peak file. Only shapes as produced by [`Self::generate`] or
+    /// [`super::WaveformShapeBuilder`] can be stored, since points are assumed to cover evenly
+    /// sized runs of samples. Values beyond ±1 are clamped.
+    pub fn write_peak_file(
+        &self,
+        mut writer: impl Write,
+        quantization: PeakQuantization,
+    ) -> io::Result<()> {
+        let Some(first) = self.channels.first().and_then(|mipmaps| mipmaps.first())
+        else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Waveform has no mipmaps",
+            ));
+        };
+        let first_mipmap_scale = (self.sample_rate as f32 / first.points_per_second).round();
+        let mipmap_scale = self.channels[0].get(1).map_or(2.0, |second| {
+            (first.points_per_second / second.points_per_second).round()
+        });
+
+        let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+        header.extend(MAGIC);
+        header.extend(PEAK_FILE_VERSION.to_le_bytes());
+        header.push(quantization as u8);
+        header.push(0);
+        header.extend((self.sample_rate as u32).to_le_bytes());
+        header.extend((self.num_samples as u64).to_le_bytes());
+        header.extend((self.num_channels() as u16).to_le_bytes());
+        header.extend([0; 2]);
+        header.extend((first_mipmap_scale as u32).to_le_bytes());
+        header.extend((mipmap_scale as u32).to_le_bytes());
+        header.extend(self.min_max.0.to_le_bytes());
+        header.extend(self.min_max.1.to_le_bytes());
+        debug_assert_eq!(header.len() as u64, HEADER_SIZE);
+
+        // The level table comes right after the header, followed by the level data
+        let table_size: usize = self.channels.iter().map(|c| 4 + c.len() * 16).sum();
+        let point_size = 5 * quantization.bytes() as u64;
+        let mut offset = HEADER_SIZE + table_size as u64;
+        for mipmaps in &self.channels {
+            header.extend((mipmaps.len() as u32).to_le_bytes());
+            for mipmap in mipmaps {
+                header.extend(offset.to_le_bytes());
+                header.extend((mipmap.len() as u64).to_le_bytes());
+                offset += mipmap.len() as u64 * point_size;
+            }
+        }
+        writer.write_all(&header)?;
+
+        let mut data = Vec::new();
+        for mipmaps in &self.channels {
+            for (level, mipmap) in mipmaps.iter().enumerate() {
+                data.clear();
+                let samples_per_point =
+                    first_mipmap_scale as usize * (mipmap_scale as usize).pow(level as u32);
+                for i in 0..mipmap.len() {
+                    let start = (i * samples_per_point) as f64;
+                    let last = (mipmap.sample_count(i).max(1) - 1) as f64;
+                    let fraction = |p: egui::Vec2| {
+                        if last > 0.0 {
+                            ((p.x as f64 * self.sample_rate as f64 - start) / last) as f32
+                        }
+                        else {
+                            0.0
+                        }
+                    };
+
+                    let (positive, negative) = (mipmap.positive_peaks[i], mipmap.negative_peaks[i]);
+                    quantization.write_fraction(&mut data, fraction(positive));
+                    quantization.write_value(&mut data, positive.y);
+                    quantization.write_fraction(&mut data, fraction(negative));
+                    quantization.write_value(&mut data, negative.y);
+                    quantization.write_value(&mut data, mipmap.rms[i]);
+                }
+                writer.write_all(&data)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads a whole peak file written by [`Self::write_peak_file`]
+    pub fn read_peak_file(reader: impl Read + Seek) -> io::Result<Self> {
+        PeakFileReader::new(reader)?.read_shape(0)
+    }
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Reads the levels of a peak file on demand. To memory map a file, wrap the mapped bytes in a
+/// [`std::io::Cursor`].
+pub struct PeakFileReader<R> {
+    reader: R,
+    header: PeakFileHeader,
+}
+impl<R: Read + Seek> PeakFileReader<R> {
+    /// Reads the header and level table. Sizes and offsets are checked against the length of
+    /// the stream, so corrupt files fail with [`io::ErrorKind::InvalidData`].
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let stream_len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+        let mut bytes = [0; HEADER_SIZE as usize];
+        reader.read_exact(&mut bytes)?;
+
+        if bytes[0..4] != MAGIC {
+            return Err(invalid_data("Not a peak file"));
+        }
+        let u16_at = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+        let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let version = u16_at(4);
+        if version != PEAK_FILE_VERSION {
+            return Err(invalid_data(format!(
+                "Unsupported peak file version {version}"
+            )));
+        }
+        let quantization = match bytes[6] {
+            0 => PeakQuantization::I8,
+            1 => PeakQuantization::I16,
+            other => return Err(invalid_data(format!("Unknown peak quantization {other}"))),
+        };
+        let sample_rate = u32_at(8) as usize;
+        let num_samples = u64::from_le_bytes(bytes[12..20].try_into().unwrap());
+        let num_channels = u16_at(20) as usize;
+        let first_mipmap_scale = u32_at(24) as usize;
+        let mipmap_scale = u32_at(28) as usize;
+        if sample_rate == 0 || num_channels == 0 || first_mipmap_scale == 0 || mipmap_scale == 0 {
+            return Err(invalid_data(
+                "Sample rate, channels and mipmap scales must be positive",
+            ));
+        }
+
+        let point_size = 5 * quantization.bytes() as u64;
+        let mut table_end = HEADER_SIZE;
+        let mut levels = Vec::with_capacity(num_channels);
+        for _ in 0..num_channels {
+            let mut count = [0; 4];
+            reader.read_exact(&mut count)?;
+            let count = u32::from_le_bytes(count) as u64;
+            table_end = (count * 16)
+                .checked_add(table_end + 4)
+                .filter(|end| *end <= stream_len)
+                .ok_or_else(|| invalid_data("Level table is truncated"))?;
+            if count == 0 {
+                return Err(invalid_data("Channel without mipmap levels"));
+            }
+
+            let mut table = vec![0; count as usize * 16];
+            reader.read_exact(&mut table)?;
+            let mut channel_levels = Vec::with_capacity(count as usize);
+            for (level, entry) in table.chunks_exact(16).enumerate() {
+                let entry = LevelEntry {
+                    offset:     u64::from_le_bytes(entry[0..8].try_into().unwrap()),
+                    num_points: u64::from_le_bytes(entry[8..16].try_into().unwrap()),
+                };
+                let samples_per_point = u32::try_from(level)
+                    .ok()
+                    .and_then(|level| mipmap_scale.checked_pow(level))
+                    .and_then(|scale| scale.checked_mul(first_mipmap_scale))
+                    .ok_or_else(|| invalid_data("Too many mipmap levels"))?;
+                let in_bounds = entry
+                    .num_points
+                    .checked_mul(point_size)
+                    .and_then(|size| size.checked_add(entry.offset))
+                    .is_some_and(|end| end <= stream_len);
+                if !in_bounds {
+                    return Err(invalid_data("Level data is out of bounds"));
+                }
+                if entry.num_points > num_samples.div_ceil(samples_per_point as u64) {
+                    return Err(invalid_data("Level has more points than samples"));
+                }
+                channel_levels.push(entry);
+            }
+            levels.push(channel_levels);
+        }
+
+        let header = PeakFileHeader {
+            version,
+            quantization,
+            sample_rate,
+            num_samples: num_samples as usize,
+            num_channels,
+            first_mipmap_scale,
+            mipmap_scale,
+            min_max: (
+                f32::from_le_bytes(bytes[32..36].try_into().unwrap()),
+                f32::from_le_bytes(bytes[36..40].try_into().unwrap()),
+            ),
+            levels,
+        };
+        Ok(Self { reader, header })
+    }
+
+    pub fn header(&self) -> &PeakFileHeader { &self.header }
+
+    /// Reads a single mipmap level of `channel`, 0 being the finest
+    pub fn read_level(&mut self, channel: usize, level: usize) -> io::Result<WaveformMipmap> {
+        let header = &self.header;
+        let entry = *header
+            .levels
+            .get(channel)
+            .and_then(|levels| levels.get(level))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No such level"))?;
+
+        let quantization = header.quantization;
+        let width = quantization.bytes();
+        let mut data = vec![0; entry.num_points as usize * 5 * width];
+        self.reader.seek(SeekFrom::Start(entry.offset))?;
+        self.reader.read_exact(&mut data)?;
+
+        let sample_rate = header.sample_rate as f64;
+        let samples_per_point = header.samples_per_point(level);
+        let mut mipmap = WaveformMipmap::with_capacity(
+            entry.num_points as usize,
+            (sample_rate / samples_per_point as f64) as f32,
+        )
+        .with_samples_per_point(samples_per_point);
+        for (i, point) in data.chunks_exact(5 * width).enumerate() {
+            let start = i * samples_per_point;
+            let sample_count = samples_per_point.min(header.num_samples.saturating_sub(start));
+            let field = |n: usize| &point[n * width..(n + 1) * width];
+            let peak = |fraction: f32, value: f32| {
+                let index = (fraction as f64 * sample_count.saturating_sub(1) as f64).round();
+                egui::vec2(((start as f64 + index) / sample_rate) as f32, value)
+            };
+
+            mipmap.positive_peaks.push(peak(
+                quantization.read_fraction(field(0)),
+                quantization.read_value(field(1)),
+            ));
+            mipmap.negative_peaks.push(peak(
+                quantization.read_fraction(field(2)),
+                quantization.read_value(field(3)),
+            ));
+            mipmap.rms.push(quantization.read_value(field(4)));
+            mipmap.num_samples += sample_count;
+        }
+        Ok(mipmap)
+    }
+
+    /// Reads the levels from `finest_level` on into a shape. Starting at a coarser level loads
+    /// faster and uses less memory, at the cost of detail when zoomed in.
+    pub fn read_shape(&mut self, finest_level: usize) -> io::Result<WaveformShape> {
+        let mut channels = Vec::with_capacity(self.header.num_channels);
+        for channel in 0..self.header.num_channels {
+            let num_levels = self.header.num_levels(channel);
+            let mipmaps = (finest_level.min(num_levels.saturating_sub(1))..num_levels)
+                .map(|level| self.read_level(channel, level))
+                .collect::<io::Result<Vec<_>>>()?;
+            channels.push(mipmaps);
+        }
+
+        Ok(WaveformShape {
+            sample_rate: self.header.sample_rate,
+            num_samples: self.header.num_samples,
+            channels,
+            min_max: self.header.min_max,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn test_shape() -> WaveformShape {
+        let left: Vec<f32> = (0..50_001).map(|i| (i as f32 * 0.01).sin() * 0.8).collect();
+        let right: Vec<f32> = (0..50_001)
+            .map(|i| (i as f32 * 0.003).cos() * 0.5)
+            .collect();
+        WaveformShape::generate_planar(&[&left, &right], 48000, 4, 3)
+    }
+
+    fn write(shape: &WaveformShape, quantization: PeakQuantization) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        shape.write_peak_file(&mut bytes, quantization).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let shape = test_shape();
+        for quantization in [PeakQuantization::I8, PeakQuantization::I16] {
+            let read =
+                WaveformShape::read_peak_file(Cursor::new(write(&shape, quantization))).unwrap();
+            assert_eq!(read.sample_rate, shape.sample_rate);
+            assert_eq!(read.num_samples, shape.num_samples);
+            assert_eq!(read.min_max, shape.min_max);
+
+            let step = 1.0 / quantization.max();
+            for channel in 0..shape.num_channels() {
+                let (levels, expected_levels) = (
+                    read.channel_mipmaps(channel),
+                    shape.channel_mipmaps(channel),
+                );
+                assert_eq!(levels.len(), expected_levels.len());
+                for (level, expected) in levels.iter().zip(expected_levels) {
+                    assert_eq!(level.len(), expected.len());
+                    assert_eq!(level.num_samples, expected.num_samples);
+                    // Peaks move by at most a fraction of the point they are in
+                    let max_time_error = level.samples_per_point as f32 / 48000.0;
+                    let peaks = level.positive_peaks.iter().chain(&level.negative_peaks);
+                    let expected_peaks = expected
+                        .positive_peaks
+                        .iter()
+                        .chain(&expected.negative_peaks);
+                    for (peak, expected_peak) in peaks.zip(expected_peaks) {
+                        assert!((peak.y - expected_peak.y).abs() <= step);
+                        assert!((peak.x - expected_peak.x).abs() <= max_time_error);
+                    }
+                    for (rms, expected_rms) in level.rms.iter().zip(&expected.rms) {
+                        assert!((rms - expected_rms).abs() <= step);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut bytes = write(&test_shape(), PeakQuantization::I16);
+        bytes[0] = b'X';
+        let error = PeakFileReader::new(Cursor::new(bytes)).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_unknown_version() {
+        let mut bytes = write(&test_shape(), PeakQuantization::I16);
+        bytes[4..6].copy_from_slice(&(PEAK_FILE_VERSION + 1).to_le_bytes());
+        let error = PeakFileReader::new(Cursor::new(bytes)).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_corrupt_sizes() {
+        let bytes = write(&test_shape(), PeakQuantization::I16);
+
+        let mut zero_sample_rate = bytes.clone();
+        zero_sample_rate[8..12].fill(0);
+        // Point count of the finest level of the first channel
+        let mut huge_level = bytes.clone();
+        let num_points = HEADER_SIZE as usize + 4 + 8;
+        huge_level[num_points..num_points + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        let mut truncated = bytes;
+        truncated.truncate(truncated.len() - 1);
+
+        for bytes in [zero_sample_rate, huge_level, truncated] {
+            let error = PeakFileReader::new(Cursor::new(bytes)).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,352 @@
+use crate::{TempoMap, WaveformItem, WaveformMarker};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SnapGrid {
+    /// Multiples of the given number of seconds
+    Seconds(f32),
+    /// Beats of the tempo map, each divided into the given number of subdivisions
+    Beats(u32),
+    /// Bar starts of the tempo map
+    Bars,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapKind {
+    ZeroCrossing,
+    Grid,
+    Marker,
+    Item,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SnapTarget {
+    pub time: f32,
+    pub kind: SnapKind,
+}
+
+/// Configures what times dragged in a [`crate::Waveform`] snap to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WaveformSnap {
+    /// Zero crossings in any channel of the items. Items being trimmed snap to their own zero
+    /// crossings, even beyond their current edges.
+    pub zero_crossings: bool,
+    pub grid: Option<SnapGrid>,
+    pub markers: bool,
+    pub items: bool,
+    /// Maximum distance in points a time gets pulled towards a snap target
+    pub radius: f32,
+    /// Holding these modifiers temporarily disables snapping
+    pub bypass_modifiers: egui::Modifiers,
+}
+impl Default for WaveformSnap {
+    fn default() -> Self {
+        Self {
+            zero_crossings: false,
+            grid: None,
+            markers: false,
+            items: false,
+            radius: 8.0,
+            bypass_modifiers: egui::Modifiers::ALT,
+        }
+    }
+}
+impl WaveformSnap {
+    pub fn with_zero_crossings(self, zero_crossings: bool) -> Self {
+        Self {
+            zero_crossings,
+            ..self
+        }
+    }
+
+    pub fn with_grid(self, grid: SnapGrid) -> Self {
+        Self {
+            grid: Some(grid),
+            ..self
+        }
+    }
+
+    pub fn with_markers(self, markers: bool) -> Self { Self { markers, ..self } }
+
+    pub fn with_items(self, items: bool) -> Self { Self { items, ..self } }
+
+    pub fn with_radius(self, radius: f32) -> Self { Self { radius, ..self } }
+
+    pub fn with_bypass_modifiers(self, bypass_modifiers: egui::Modifiers) -> Self {
+        Self {
+            bypass_modifiers,
+            ..self
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.zero_crossings || self.grid.is_some() || self.markers || self.items
+    }
+
+    pub(crate) fn is_bypassed(&self, modifiers: egui::Modifiers) -> bool {
+        !self.bypass_modifiers.is_none() && modifiers.contains(self.bypass_modifiers)
+    }
+
+    /// Closest snap target within `radius` points of `time`
+    pub(crate) fn snap(
+        &self,
+        time: f32,
+        seconds_per_pixel: f32,
+        sources: &SnapSources,
+    ) -> Option<SnapTarget> {
+        let max_distance = self.radius * seconds_per_pixel;
+        let mut candidates = Vec::new();
+
+        if let Some(grid) = self.grid {
+            candidates.extend(grid_candidates(grid, time, sources.tempo_map).map(|time| {
+                SnapTarget {
+                    time,
+                    kind: SnapKind::Grid,
+                }
+            }));
+        }
+
+        if self.markers {
+            let markers = Iterator::chain(
+                sources.markers.iter(),
+                sources
+                    .editable_markers
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| Some(*i) != sources.skip_marker)
+                    .map(|(_, m)| m),
+            );
+            for m in markers {
+                candidates.push(SnapTarget {
+                    time: m.start,
+                    kind: SnapKind::Marker,
+                });
+                if let Some(end) = m.end {
+                    candidates.push(SnapTarget {
+                        time: end,
+                        kind: SnapKind::Marker,
+                    });
+                }
+            }
+        }
+
+        let dragged = |i: usize| sources.dragged_item.filter(|dragged| dragged.index == i);
+
+        if self.items {
+            let items = sources
+                .items
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| dragged(*i).is_none())
+                .map(|(_, item)| item);
+            for item in items {
+                let range = item.time_range();
+                candidates.push(SnapTarget {
+                    time: range.start,
+                    kind: SnapKind::Item,
+                });
+                candidates.push(SnapTarget {
+                    time: range.end,
+                    kind: SnapKind::Item,
+                });
+            }
+        }
+
+        if self.zero_crossings {
+            for (i, item) in sources.items.iter().enumerate() {
+                let offset = item.position - item.source_offset;
+                // A trimmed item can grow over all of its source, a moved one takes its own zero
+                // crossings along
+                let range = match dragged(i) {
+                    Some(DraggedItem { moving: true, .. }) => continue,
+                    Some(_) => offset..offset + item.waveform.len_seconds(),
+                    None => item.time_range(),
+                };
+                if !range.contains(&time) {
+                    continue;
+                }
+                // Zero crossings of any channel
+                let crossings = (0..item.waveform.num_channels()).filter_map(|channel| {
+                    item.waveform
+                        .nearest_zero_crossing(channel, time - offset, max_distance)
+                });
+                candidates.extend(crossings.map(|crossing| SnapTarget {
+                    time: crossing + offset,
+                    kind: SnapKind::ZeroCrossing,
+                }));
+            }
+        }
+
+        candidates
+            .into_iter()
+            .filter(|c| (c.time - time).abs() <= max_distance)
+            .min_by(|a, b| (a.time - time).abs().total_cmp(&(b.time - time).abs()))
+    }
+}
+
+/// Everything a time can snap to, minus the marker or item edges that are being dragged
+pub(crate) struct SnapSources<'s> {
+    pub items: &'s [WaveformItem<'s>],
+    pub markers: &'s [WaveformMarker],
+    pub editable_markers: &'s [WaveformMarker],
+    pub tempo_map: Option<&'s TempoMap>,
+    pub skip_marker: Option<usize>,
+    pub dragged_item: Option<DraggedItem>,
+}
+
+/// Item whose edge or fade is being dragged, or which is `moving` as a whole
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DraggedItem {
+    pub index:  usize,
+    pub moving: bool,
+}
+
+/// The grid lines on both sides of `time`
+fn grid_candidates(
+    grid: SnapGrid,
+    time: f32,
+    tempo_map: Option<&TempoMap>,
+) -> impl Iterator<Item = f32> {
+    let candidates = match (grid, tempo_map) {
+        (SnapGrid::Seconds(step), _) if step > 0.0 => {
+            let below = (time / step).floor() * step;
+            Some([below, below + step])
+        }
+        (SnapGrid::Beats(subdivisions), Some(tempo_map)) => {
+            let quarters = tempo_map.seconds_to_quarters(time);
+            let bar = tempo_map.quarters_to_bar(quarters);
+            let bar_start = tempo_map.bar_to_quarters(bar);
+            let step = tempo_map.time_signature_at_bar(bar).quarters_per_beat()
+                / subdivisions.max(1) as f64;
+            let below = bar_start + ((quarters - bar_start) / step).floor() * step;
+            Some([
+                tempo_map.quarters_to_seconds(below),
+                tempo_map.quarters_to_seconds(below + step),
+            ])
+        }
+        (SnapGrid::Bars, Some(tempo_map)) => {
+            let bar = tempo_map.quarters_to_bar(tempo_map.seconds_to_quarters(time));
+            Some([
+                tempo_map.quarters_to_seconds(tempo_map.bar_to_quarters(bar)),
+                tempo_map.quarters_to_seconds(tempo_map.bar_to_quarters(bar + 1)),
+            ])
+        }
+        _ => None,
+    };
+
+    candidates.into_iter().flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WaveformShape;
+
+    /// 8 points of radius at 1 ms per pixel
+    const SECONDS_PER_PIXEL: f32 = 0.001;
+
+    fn sources<'s>(
+        items: &'s [WaveformItem<'s>],
+        editable_markers: &'s [WaveformMarker],
+        dragged_item: Option<DraggedItem>,
+    ) -> SnapSources<'s> {
+        SnapSources {
+            items,
+            markers: &[],
+            editable_markers,
+            tempo_map: None,
+            skip_marker: None,
+            dragged_item,
+        }
+    }
+
+    /// One second at 1 kHz with a silent first channel and a 10 Hz cosine in the second, which
+    /// crosses zero at 25 ms + n * 50 ms
+    fn shape() -> WaveformShape {
+        let silence = vec![0.0f32; 1000];
+        let cosine: Vec<f32> = (0..1000)
+            .map(|i| (i as f32 / 1000.0 * 10.0 * std::f32::consts::TAU).cos())
+            .collect();
+        WaveformShape::generate_planar(&[&silence, &cosine], 1000, 1, 2)
+    }
+
+    fn assert_snaps(target: Option<SnapTarget>, time: f32, kind: SnapKind) {
+        let target = target.unwrap();
+        assert_eq!(target.kind, kind);
+        assert!(
+            (target.time - time).abs() < 1e-3,
+            "{} != {time}",
+            target.time
+        );
+    }
+
+    #[test]
+    fn grid_and_markers() {
+        let snap = WaveformSnap::default()
+            .with_grid(SnapGrid::Seconds(1.0))
+            .with_markers(true);
+        let markers = [WaveformMarker::from_position(2.001)];
+
+        let target = snap.snap(2.004, SECONDS_PER_PIXEL, &sources(&[], &markers, None));
+        assert_snaps(target, 2.001, SnapKind::Marker);
+
+        // The dragged marker doesn't snap to itself
+        let sources = SnapSources {
+            skip_marker: Some(0),
+            ..sources(&[], &markers, None)
+        };
+        assert_snaps(
+            snap.snap(2.004, SECONDS_PER_PIXEL, &sources),
+            2.0,
+            SnapKind::Grid,
+        );
+        assert_eq!(snap.snap(2.5, SECONDS_PER_PIXEL, &sources), None);
+    }
+
+    #[test]
+    fn item_edges() {
+        let shape = shape();
+        let items = [WaveformItem::new(&shape).with_position(10.0)];
+        let snap = WaveformSnap::default().with_items(true);
+
+        let target = snap.snap(10.003, SECONDS_PER_PIXEL, &sources(&items, &[], None));
+        assert_snaps(target, 10.0, SnapKind::Item);
+
+        let dragged = Some(DraggedItem {
+            index:  0,
+            moving: false,
+        });
+        let target = snap.snap(10.003, SECONDS_PER_PIXEL, &sources(&items, &[], dragged));
+        assert_eq!(target, None);
+    }
+
+    #[test]
+    fn zero_crossings() {
+        let shape = shape();
+        // Plays 0.5 to 0.7 seconds of the source from 10 seconds on
+        let items = [WaveformItem::new(&shape)
+            .with_position(10.0)
+            .with_source_offset(0.5)
+            .with_length(0.2)];
+        let snap = WaveformSnap::default().with_zero_crossings(true);
+        let snap_with = |time: f32, dragged_item: Option<DraggedItem>| {
+            snap.snap(time, SECONDS_PER_PIXEL, &sources(&items, &[], dragged_item))
+        };
+
+        // Inside the item, using the second channel
+        assert_snaps(snap_with(10.07, None), 10.075, SnapKind::ZeroCrossing);
+        // Outside of it only while trimming, since the item can grow over its whole source
+        assert_eq!(snap_with(9.83, None), None);
+        let trimmed = Some(DraggedItem {
+            index:  0,
+            moving: false,
+        });
+        assert_snaps(snap_with(9.83, trimmed), 9.825, SnapKind::ZeroCrossing);
+        assert_snaps(snap_with(10.07, trimmed), 10.075, SnapKind::ZeroCrossing);
+        // A moved item's zero crossings move along with it
+        let moved = Some(DraggedItem {
+            index:  0,
+            moving: true,
+        });
+        assert_eq!(snap_with(10.07, moved), None);
+    }
+}
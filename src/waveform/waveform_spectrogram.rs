@@ -0,0 +1,307 @@
+use std::hash::{Hash, Hasher};
+use std::ops::{Range, RangeInclusive};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::fft::magnitude_spectrum;
+use crate::util::to_db_deadzone;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Colormap {
+    #[default]
+    Magma,
+    Viridis,
+    Grayscale,
+}
+impl Colormap {
+    /// Color for `t` in `0..=1`
+    pub fn color(&self, t: f32) -> egui::Color32 {
+        const MAGMA: [[u8; 3]; 9] = [
+            [0, 0, 4],
+            [28, 16, 68],
+            [79, 18, 123],
+            [129, 37, 129],
+            [181, 54, 122],
+            [229, 80, 100],
+            [251, 135, 97],
+            [254, 194, 135],
+            [252, 253, 191],
+        ];
+        const VIRIDIS: [[u8; 3]; 9] = [
+            [68, 1, 84],
+            [71, 44, 122],
+            [59, 81, 139],
+            [44, 113, 142],
+            [33, 144, 141],
+            [39, 173, 129],
+            [92, 200, 99],
+            [170, 220, 50],
+            [253, 231, 37],
+        ];
+
+        let t = t.clamp(0.0, 1.0);
+        let table = match self {
+            Colormap::Magma => &MAGMA,
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Grayscale => {
+                let v = (t * 255.0).round() as u8;
+                return egui::Color32::from_gray(v);
+            }
+        };
+
+        let position = t * (table.len() - 1) as f32;
+        let i = (position.floor() as usize).min(table.len() - 2);
+        let [a, b] = [table[i], table[i + 1]];
+        let f = position - i as f32;
+        let channel = |c: usize| egui::lerp(a[c] as f32..=b[c] as f32, f).round() as u8;
+        egui::Color32::from_rgb(channel(0), channel(1), channel(2))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FrequencyScale {
+    Linear,
+    /// From 20 Hz up
+    #[default]
+    Logarithmic,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpectrogramStyle {
+    pub colormap: Colormap,
+    pub frequency_scale: FrequencyScale,
+    /// Levels mapped to the ends of the colormap
+    pub db_range: RangeInclusive<f32>,
+}
+impl Default for SpectrogramStyle {
+    fn default() -> Self {
+        Self {
+            colormap: Colormap::default(),
+            frequency_scale: FrequencyScale::default(),
+            db_range: -100.0..=0.0,
+        }
+    }
+}
+impl SpectrogramStyle {
+    pub fn with_colormap(self, colormap: Colormap) -> Self { Self { colormap, ..self } }
+
+    pub fn with_frequency_scale(self, frequency_scale: FrequencyScale) -> Self {
+        Self {
+            frequency_scale,
+            ..self
+        }
+    }
+
+    pub fn with_db_range(self, db_range: RangeInclusive<f32>) -> Self { Self { db_range, ..self } }
+}
+
+/// STFT frames at one time resolution
+#[derive(Debug, Clone)]
+pub struct SpectrogramLevel {
+    pub frames_per_second: f32,
+    /// Time of the center of the first frame
+    pub start_time: f32,
+    /// Linear magnitudes, `num_bins` per frame
+    pub magnitudes: Vec<f32>,
+}
+impl SpectrogramLevel {
+    /// Averages the power of neighbouring frames, halving the time resolution
+    fn shrink(&self, num_bins: usize) -> Self {
+        let magnitudes = self
+            .magnitudes
+            .chunks(num_bins * 2)
+            .flat_map(|frames| {
+                let (a, b) = frames.split_at(num_bins.min(frames.len()));
+                (0..num_bins).map(move |bin| {
+                    let b = b.get(bin).copied().unwrap_or(a[bin]);
+                    ((a[bin] * a[bin] + b * b) / 2.0).sqrt()
+                })
+            })
+            .collect();
+
+        Self {
+            frames_per_second: self.frames_per_second / 2.0,
+            start_time: self.start_time + 0.5 / self.frames_per_second,
+            magnitudes,
+        }
+    }
+
+    pub fn num_frames(&self, num_bins: usize) -> usize { self.magnitudes.len() / num_bins }
+}
+
+/// Short-time spectrum of a single channel, with levels of decreasing time resolution like the
+/// mipmaps of a [`crate::WaveformShape`].
+#[derive(Debug, Clone)]
+pub struct WaveformSpectrogram {
+    pub sample_rate: usize,
+    pub fft_size: usize,
+    pub levels: Vec<SpectrogramLevel>,
+    /// Unique for every call to [`Self::generate`], so cached textures of an older spectrogram
+    /// aren't drawn for a new one at the same address
+    generation: u64,
+}
+impl WaveformSpectrogram {
+    /// `fft_size` has to be a power of two of at least 2. Frames are `hop` samples apart at the
+    /// finest level.
+    pub fn generate(samples: &[f32], sample_rate: usize, fft_size: usize, hop: usize) -> Self {
+        assert!(
+            fft_size.is_power_of_two(),
+            "FFT size must be a power of two"
+        );
+        assert!(fft_size >= 2, "FFT size must be at least 2");
+        assert!(hop > 0, "Hop size must be positive");
+
+        let num_bins = fft_size / 2 + 1;
+        let num_frames = samples.len().div_ceil(hop).max(1);
+        let mut frame = vec![0.0; fft_size];
+        let mut magnitudes = Vec::with_capacity(num_frames * num_bins);
+
+        // Frames are centered on multiples of `hop`, zero padded at the edges
+        for i in 0..num_frames {
+            let center = (i * hop) as isize;
+            for (j, sample) in frame.iter_mut().enumerate() {
+                let index = center + j as isize - (fft_size / 2) as isize;
+                *sample = usize::try_from(index)
+                    .ok()
+                    .and_then(|index| samples.get(index))
+                    .copied()
+                    .unwrap_or(0.0);
+            }
+            magnitudes.extend(magnitude_spectrum(&frame, fft_size));
+        }
+
+        let mut levels = vec![SpectrogramLevel {
+            frames_per_second: sample_rate as f32 / hop as f32,
+            start_time: 0.0,
+            magnitudes,
+        }];
+        while levels.last().unwrap().num_frames(num_bins) > 64 {
+            let next = levels.last().unwrap().shrink(num_bins);
+            levels.push(next);
+        }
+
+        static NEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
+        Self {
+            sample_rate,
+            fft_size,
+            levels,
+            generation: NEXT_GENERATION.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    pub fn num_bins(&self) -> usize { self.fft_size / 2 + 1 }
+
+    pub fn len_seconds(&self) -> f32 {
+        let level = &self.levels[0];
+        level.num_frames(self.num_bins()) as f32 / level.frames_per_second
+    }
+
+    /// Coarsest level with at least `frames_per_second` frames per second
+    fn level_for(&self, frames_per_second: f32) -> &SpectrogramLevel {
+        self.levels
+            .iter()
+            .take_while(|level| level.frames_per_second >= frames_per_second)
+            .last()
+            .unwrap_or(&self.levels[0])
+    }
+
+    /// Renders `time_range` into an image of `size` pixels with low frequencies at the bottom
+    pub fn image(
+        &self,
+        time_range: Range<f32>,
+        size: [usize; 2],
+        style: &SpectrogramStyle,
+    ) -> egui::ColorImage {
+        let [width, height] = size;
+        let num_bins = self.num_bins();
+        let duration = time_range.end - time_range.start;
+        let level = self.level_for(width as f32 / duration);
+        let num_frames = level.num_frames(num_bins);
+
+        let nyquist = self.sample_rate as f32 / 2.0;
+        let bin_per_hz = self.fft_size as f32 / self.sample_rate as f32;
+        let row_bins: Vec<f32> = (0..height)
+            .map(|row| {
+                let t = 1.0 - (row as f32 + 0.5) / height as f32;
+                let frequency = match style.frequency_scale {
+                    FrequencyScale::Linear => t * nyquist,
+                    FrequencyScale::Logarithmic => 20.0 * (nyquist / 20.0).powf(t),
+                };
+                frequency * bin_per_hz
+            })
+            .collect();
+
+        let mut pixels = vec![egui::Color32::TRANSPARENT; width * height];
+        for column in 0..width {
+            let time = time_range.start + (column as f32 + 0.5) / width as f32 * duration;
+            let frame = ((time - level.start_time) * level.frames_per_second).round();
+            if frame < 0.0 || frame as usize >= num_frames {
+                continue;
+            }
+            let frame = &level.magnitudes[frame as usize * num_bins..][..num_bins];
+
+            for (row, bin) in row_bins.iter().enumerate() {
+                let i = (bin.floor() as usize).min(num_bins - 2);
+                let magnitude = egui::lerp(frame[i]..=frame[i + 1], bin - i as f32);
+                let db = to_db_deadzone(magnitude, *style.db_range.start());
+                pixels[row * width + column] =
+                    style
+                        .colormap
+                        .color(egui::remap(db, style.db_range.clone(), 0.0..=1.0));
+            }
+        }
+
+        egui::ColorImage::new(size, pixels)
+    }
+
+    /// Draws `time_range` into `rect`. The texture is cached under `id` and only rebuilt when
+    /// something changes.
+    pub(crate) fn paint(
+        &self,
+        ui: &egui::Ui,
+        painter: &egui::Painter,
+        id: egui::Id,
+        rect: egui::Rect,
+        time_range: Range<f32>,
+        style: &SpectrogramStyle,
+    ) {
+        let size = [
+            (rect.width().round() as usize).clamp(1, 4096),
+            (rect.height().round() as usize).clamp(1, 1024),
+        ];
+
+        let mut hasher = std::hash::DefaultHasher::new();
+        self.generation.hash(&mut hasher);
+        [
+            time_range.start,
+            time_range.end,
+            *style.db_range.start(),
+            *style.db_range.end(),
+        ]
+        .map(f32::to_bits)
+        .hash(&mut hasher);
+        (size, style.colormap, style.frequency_scale).hash(&mut hasher);
+        let key = hasher.finish();
+
+        let cached = ui.data(|d| d.get_temp::<(u64, egui::TextureHandle)>(id));
+        let texture = match cached {
+            Some((cached_key, texture)) if cached_key == key => texture,
+            _ => {
+                let image = self.image(time_range, size, style);
+                let texture = ui.ctx().load_texture(
+                    format!("spectrogram_{id:?}"),
+                    image,
+                    egui::TextureOptions::LINEAR,
+                );
+                ui.data_mut(|d| d.insert_temp(id, (key, texture.clone())));
+                texture
+            }
+        };
+
+        painter.image(
+            texture.id(),
+            rect,
+            egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+            egui::Color32::WHITE,
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use super::{WaveformShape, WaveformShapeBuilder};
+use crate::{I24, Sample};
+
+/// Frames read at a time by [`WaveformShape::from_wav`]
+const CHUNK_FRAMES: usize = 1 << 16;
+
+/// Reads a WAV file in chunks of interleaved samples normalized to `-1.0..=1.0`. Supports 8, 16,
+/// 24 and 32 bit integer PCM and 32 bit float, with any number of channels.
+pub struct WavChunkReader<R> {
+    reader: hound::WavReader<R>,
+    chunk_frames: usize,
+    buffer: Vec<f32>,
+}
+impl WavChunkReader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>, chunk_frames: usize) -> Result<Self, hound::Error> {
+        Self::from_wav_reader(hound::WavReader::open(path)?, chunk_frames)
+    }
+}
+impl<R: Read> WavChunkReader<R> {
+    pub fn new(reader: R, chunk_frames: usize) -> Result<Self, hound::Error> {
+        Self::from_wav_reader(hound::WavReader::new(reader)?, chunk_frames)
+    }
+
+    fn from_wav_reader(
+        reader: hound::WavReader<R>,
+        chunk_frames: usize,
+    ) -> Result<Self, hound::Error> {
+        let spec = reader.spec();
+        match (spec.sample_format, spec.bits_per_sample) {
+            (hound::SampleFormat::Int, 8 | 16 | 24 | 32) | (hound::SampleFormat::Float, 32) => {}
+            _ => return Err(hound::Error::Unsupported),
+        }
+        assert!(chunk_frames > 0, "Chunks must hold at least one frame");
+
+        Ok(Self {
+            reader,
+            chunk_frames,
+            buffer: Vec::with_capacity(chunk_frames * spec.channels as usize),
+        })
+    }
+
+    pub fn sample_rate(&self) -> usize { self.reader.spec().sample_rate as usize }
+
+    pub fn num_channels(&self) -> usize { self.reader.spec().channels as usize }
+
+    /// Number of frames, i.e. samples per channel
+    pub fn num_frames(&self) -> usize { self.reader.duration() as usize }
+
+    /// The next chunk of interleaved samples, or `None` at the end of the file
+    pub fn next_chunk(&mut self) -> Option<Result<&[f32], hound::Error>> {
+        let spec = self.reader.spec();
+        let len = self.chunk_frames * spec.channels as usize;
+        self.buffer.clear();
+
+        let result = match (spec.sample_format, spec.bits_per_sample) {
+            (hound::SampleFormat::Float, _) => self.read_into::<f32, f32>(len, |x| x),
+            // hound already turns unsigned 8 bit samples into signed ones
+            (_, 8) => self.read_into::<i8, i8>(len, |x| x),
+            (_, 16) => self.read_into::<i16, i16>(len, |x| x),
+            (_, 24) => self.read_into::<i32, I24>(len, I24),
+            _ => self.read_into::<i32, i32>(len, |x| x),
+        };
+
+        match result {
+            Err(err) => Some(Err(err)),
+            Ok(()) if self.buffer.is_empty() => None,
+            Ok(()) => Some(Ok(&self.buffer)),
+        }
+    }
+
+    fn read_into<T: hound::Sample, S: Sample>(
+        &mut self,
+        len: usize,
+        convert: impl Fn(T) -> S,
+    ) -> Result<(), hound::Error> {
+        for sample in self.reader.samples::<T>().take(len) {
+            self.buffer.push(convert(sample?).to_f32());
+        }
+        Ok(())
+    }
+}
+
+impl WaveformShape {
+    /// Reads a WAV file chunk by chunk, so the samples are never all in memory at once
+    pub fn from_wav(
+        reader: impl Read,
+        first_mipmap_scale: usize,
+        mipmap_scale: usize,
+    ) -> Result<Self, hound::Error> {
+        Self::from_wav_chunks(
+            WavChunkReader::new(reader, CHUNK_FRAMES)?,
+            first_mipmap_scale,
+            mipmap_scale,
+        )
+    }
+
+    pub fn from_wav_path(
+        path: impl AsRef<Path>,
+        first_mipmap_scale: usize,
+        mipmap_scale: usize,
+    ) -> Result<Self, hound::Error> {
+        Self::from_wav_chunks(
+            WavChunkReader::open(path, CHUNK_FRAMES)?,
+            first_mipmap_scale,
+            mipmap_scale,
+        )
+    }
+
+    fn from_wav_chunks<R: Read>(
+        mut chunks: WavChunkReader<R>,
+        first_mipmap_scale: usize,
+        mipmap_scale: usize,
+    ) -> Result<Self, hound::Error> {
+        let mut builder = WaveformShapeBuilder::new(
+            chunks.num_channels(),
+            chunks.sample_rate(),
+            first_mipmap_scale,
+            mipmap_scale,
+        );
+        while let Some(chunk) = chunks.next_chunk() {
+            builder.push_interleaved(chunk?);
+        }
+        Ok(builder.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const NUM_FRAMES: usize = 3001;
+
+    /// Stereo WAV of a full scale ramp on the left channel and its inverse on the right, and the
+    /// expected normalized samples
+    fn write_wav<T: hound::Sample + Copy>(
+        sample_format: hound::SampleFormat,
+        bits_per_sample: u16,
+        sample: impl Fn(f64) -> T,
+        normalize: impl Fn(T) -> f32,
+    ) -> (Vec<u8>, Vec<f32>) {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 8000,
+            bits_per_sample,
+            sample_format,
+        };
+        let mut bytes = Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut bytes, spec).unwrap();
+        let mut expected = Vec::new();
+        for i in 0..NUM_FRAMES {
+            let x = i as f64 / (NUM_FRAMES - 1) as f64 * 2.0 - 1.0;
+            for value in [sample(x), sample(-x)] {
+                writer.write_sample(value).unwrap();
+                expected.push(normalize(value));
+            }
+        }
+        writer.finalize().unwrap();
+        (bytes.into_inner(), expected)
+    }
+
+    fn check(bytes: Vec<u8>, expected: &[f32]) {
+        let mut reader = WavChunkReader::new(Cursor::new(&bytes), 100).unwrap();
+        assert_eq!(reader.num_channels(), 2);
+        assert_eq!(reader.sample_rate(), 8000);
+        assert_eq!(reader.num_frames(), NUM_FRAMES);
+        let mut samples = Vec::new();
+        while let Some(chunk) = reader.next_chunk() {
+            samples.extend_from_slice(chunk.unwrap());
+        }
+        assert_eq!(samples, expected);
+        assert_eq!(samples[0], -1.0);
+
+        let shape = WaveformShape::from_wav(Cursor::new(&bytes), 4, 2).unwrap();
+        let expected = WaveformShape::generate_interleaved(expected, 2, 8000, 4, 2);
+        assert_eq!(shape.num_samples, NUM_FRAMES);
+        for channel in 0..2 {
+            for (level, expected) in shape
+                .channel_mipmaps(channel)
+                .iter()
+                .zip(expected.channel_mipmaps(channel))
+            {
+                assert_eq!(level.positive_peaks, expected.positive_peaks);
+                assert_eq!(level.negative_peaks, expected.negative_peaks);
+            }
+        }
+    }
+
+    #[test]
+    fn int_8() {
+        let (bytes, expected) = write_wav(
+            hound::SampleFormat::Int,
+            8,
+            |x| (x * 128.0).clamp(-128.0, 127.0) as i8,
+            Sample::to_f32,
+        );
+        check(bytes, &expected);
+    }
+
+    #[test]
+    fn int_16() {
+        let (bytes, expected) = write_wav(
+            hound::SampleFormat::Int,
+            16,
+            |x| (x * 32768.0).clamp(-32768.0, 32767.0) as i16,
+            Sample::to_f32,
+        );
+        check(bytes, &expected);
+    }
+
+    #[test]
+    fn int_24() {
+        let (bytes, expected) = write_wav(
+            hound::SampleFormat::Int,
+            24,
+            |x| (x * 8388608.0).clamp(-8388608.0, 8388607.0) as i32,
+            |x| I24(x).to_f32(),
+        );
+        check(bytes, &expected);
+    }
+
+    #[test]
+    fn int_32() {
+        let (bytes, expected) = write_wav(
+            hound::SampleFormat::Int,
+            32,
+            |x| (x * 2147483648.0).clamp(-2147483648.0, 2147483647.0) as i32,
+            Sample::to_f32,
+        );
+        check(bytes, &expected);
+    }
+
+    #[test]
+    fn float_32() {
+        let (bytes, expected) =
+            write_wav(hound::SampleFormat::Float, 32, |x| x as f32, Sample::to_f32);
+        check(bytes, &expected);
+    }
+}